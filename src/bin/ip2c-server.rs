@@ -0,0 +1,126 @@
+//! `ip2c-server`, serves the lookups over the network, see `ip2c::server`.
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use ip2c::cli::Args;
+use ip2c::rir::parse::Delegated;
+use ip2c::rir::shared::Shared;
+use ip2c::server::dns::DnsServer;
+use ip2c::server::http::HttpServer;
+use ip2c::server::whois::WhoisServer;
+
+const USAGE: &str = "\
+Usage: ip2c-server [OPTIONS]
+
+Serve the HTTP JSON lookups on 127.0.0.1:8080 if no service is given.
+The data is loaded once for all the services, and reloaded when the files change if built with the `watch` feature.
+
+Options:
+  -d, --data <DIR>       the directory of delegated files [default: ./data]
+      --http <ADDR>      serve the HTTP JSON lookups, eg. 127.0.0.1:8080
+      --dns <ADDR>       serve the DNS TXT lookups on UDP and TCP, eg. 127.0.0.1:5353
+      --origin <NAME>    the origin of the DNS names [default: origin.ip2c.local]
+      --whois <ADDR>     serve the whois queries of addresses, ranges and ASNs, eg. 127.0.0.1:4343
+  -h, --help             print help";
+
+#[derive(Debug, PartialEq, Eq)]
+struct Options {
+    data: PathBuf,
+    http: Option<String>,
+    dns: Option<String>,
+    origin: String,
+    whois: Option<String>,
+}
+
+fn parse_args(args: impl Iterator<Item=String>) -> Result<Option<Options>, String> {
+    let mut opts = Options {
+        data: PathBuf::from("./data"),
+        http: None,
+        dns: None,
+        origin: "origin.ip2c.local".to_string(),
+        whois: None,
+    };
+    let mut args = Args::new(args);
+    while let Some(name) = args.next_name() {
+        match name.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-d" | "--data" => opts.data = args.value(&name)?.into(),
+            "--http" => opts.http = Some(args.value(&name)?),
+            "--dns" => opts.dns = Some(args.value(&name)?),
+            "--origin" => opts.origin = args.value(&name)?,
+            "--whois" => opts.whois = Some(args.value(&name)?),
+            n => return Err(format!("unknown argument: {}", n)),
+        }
+    }
+    if opts.http.is_none() && opts.dns.is_none() && opts.whois.is_none() {
+        opts.http = Some("127.0.0.1:8080".to_string());
+    }
+    Ok(Some(opts))
+}
+
+// start the services over the same data in background threads
+fn start(opts: &Options, map: &Shared<Delegated>) -> Result<Vec<std::thread::JoinHandle<std::io::Result<()>>>, Box<dyn Error>> {
+    let mut handles = Vec::new();
+    if let Some(addr) = &opts.http {
+        let server = HttpServer::bind(addr, map.clone(), Some(opts.data.clone())).map_err(|e| format!("bind {} failed: {}", addr, e))?;
+        println!("http listening on {}", server.local_addr()?);
+        handles.push(server.spawn());
+    }
+    if let Some(addr) = &opts.dns {
+        let server = DnsServer::bind(addr, map.clone(), &opts.origin).map_err(|e| format!("bind {} failed: {}", addr, e))?;
+        println!("dns listening on {}, origin {}", server.local_addr()?, opts.origin);
+        handles.push(server.spawn());
+    }
+    if let Some(addr) = &opts.whois {
+        let server = WhoisServer::bind(addr, map.clone()).map_err(|e| format!("bind {} failed: {}", addr, e))?;
+        println!("whois listening on {}", server.local_addr()?);
+        handles.push(server.spawn());
+    }
+    Ok(handles)
+}
+
+fn main() -> ExitCode {
+    let opts = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("ip2c-server: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let map = match Shared::<Delegated>::load_from_dir(&opts.data) {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("ip2c-server: load {} failed: {}", opts.data.display(), e);
+            return ExitCode::from(2);
+        }
+    };
+    let handles = match start(&opts, &map) {
+        Ok(handles) => handles,
+        Err(e) => {
+            eprintln!("ip2c-server: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+    // reload the data of all the services, kept until the services stop
+    #[cfg(feature = "watch")]
+    let _watcher = match ip2c::rir::watch::DirWatcher::new(map, &opts.data, std::time::Duration::from_secs(2)) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("ip2c-server: watch {} failed: {}", opts.data.display(), e);
+            return ExitCode::from(2);
+        }
+    };
+    for h in handles {
+        if let Ok(Err(e)) = h.join() {
+            eprintln!("ip2c-server: {}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,105 @@
+//! `--enrich`: annotate the IP literals in text lines, eg. the access logs.
+
+use std::fs;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+use ip2c::rir::parse::RecordMap;
+use ip2c::util::find_ips;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Enrich {
+    /// `1.2.3.4` to `1.2.3.4[CN]`
+    Inline,
+    /// append a tab and the codes of the addresses in the line, separated by commas
+    Column,
+}
+
+/// Enrich one line, which includes the line ending if any.
+pub fn enrich_line(mode: Enrich, map: &RecordMap, line: &[u8], out: &mut impl Write) -> io::Result<()> {
+    let (body, ending) = match line.iter().rposition(|b| *b != b'\n' && *b != b'\r') {
+        Some(n) => line.split_at(n + 1),
+        None => (&line[..0], line),
+    };
+    let mut last = 0;
+    let mut first = true;
+    for (r, ip) in find_ips(body) {
+        let code = map.query(ip).map_or("-", |r| r.code.name());
+        match mode {
+            Enrich::Inline => {
+                out.write_all(&body[last..r.end])?;
+                write!(out, "[{}]", code)?;
+                last = r.end;
+            }
+            Enrich::Column => {
+                if first {
+                    out.write_all(body)?;
+                    out.write_all(b"\t")?;
+                } else {
+                    out.write_all(b",")?;
+                }
+                out.write_all(code.as_bytes())?;
+            }
+        }
+        first = false;
+    }
+    match mode {
+        Enrich::Inline => out.write_all(&body[last..])?,
+        Enrich::Column if first => {
+            out.write_all(body)?;
+            out.write_all(b"\t")?;
+        }
+        Enrich::Column => {}
+    }
+    out.write_all(ending)
+}
+
+/// Enrich the files (`-` is stdin) line by line to stdout, the memory used is bounded by the longest line.
+pub fn run(mode: Enrich, map: &RecordMap, files: &[String]) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let stdin_only = [String::from("-")];
+    let files = if files.is_empty() { &stdin_only[..] } else { files };
+    let mut buf = Vec::new();
+    for file in files {
+        let mut r: Box<dyn BufRead> = if file == "-" {
+            Box::new(io::stdin().lock())
+        } else {
+            Box::new(BufReader::new(fs::File::open(file)?))
+        };
+        loop {
+            buf.clear();
+            if r.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            enrich_line(mode, map, &buf, &mut out)?;
+        }
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use ip2c::rir::parse::RecordMap;
+    use super::{enrich_line, Enrich};
+
+    fn enrich(mode: Enrich, line: &[u8]) -> String {
+        let mut map = RecordMap::new();
+        map.load_from_file("./data/example.txt").unwrap();
+        let mut out = Vec::new();
+        enrich_line(mode, &map, line, &mut out).unwrap();
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    #[test]
+    fn enrich_lines() {
+        let line = b"10.0.0.1 - - [18/Oct/2026:10:00:00 +0000] \"GET / HTTP/1.1\" 200 from 8.8.8.8:53\r\n";
+        assert_eq!(enrich(Enrich::Inline, line),
+                   "10.0.0.1[--] - - [18/Oct/2026:10:00:00 +0000] \"GET / HTTP/1.1\" 200 from 8.8.8.8[-]:53\r\n");
+        assert_eq!(enrich(Enrich::Column, line),
+                   "10.0.0.1 - - [18/Oct/2026:10:00:00 +0000] \"GET / HTTP/1.1\" 200 from 8.8.8.8:53\t--,-\r\n");
+        assert_eq!(enrich(Enrich::Column, b"no address\n"), "no address\t\n");
+        assert_eq!(enrich(Enrich::Inline, b"\xff127.0.0.1"), "\u{fffd}127.0.0.1[--]");
+        assert_eq!(enrich(Enrich::Inline, b""), "");
+    }
+}
@@ -0,0 +1,287 @@
+//! `ip2c` command line, resolves IP addresses to the codes of countries and regions.
+
+use std::error::Error;
+use std::fmt::Write as _;
+use std::io;
+use std::io::{BufRead, BufWriter, Write};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use ip2c::cli::Args;
+use ip2c::rir::parse::{Record, RecordMap};
+use ip2c::util::json_str;
+
+use crate::enrich::Enrich;
+
+mod enrich;
+
+const USAGE: &str = "\
+Usage: ip2c [OPTIONS] [ADDRESS]...
+       ip2c [OPTIONS] --enrich <MODE> [FILE]...
+
+Resolve the addresses to the codes of countries and regions, read from stdin if no address or `-` is given.
+With `--enrich`, find the addresses in the text lines of the files and annotate them.
+
+Options:
+  -d, --data <PATH>      the snapshot directory, whose delegated files are all loaded, or one file [default: ./data]
+  -f, --format <FORMAT>  plain, tsv or json [default: plain]
+      --registry         add the registry column
+      --state            add the state column
+  -e, --enrich <MODE>    inline (`1.2.3.4[CN]`) or column (append a tab and the codes)
+  -h, --help             print help
+
+Exit status: 0 if all addresses are mapped (always with `--enrich`), 1 if some are unmapped or invalid, 2 on errors.";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Format {
+    Plain,
+    Tsv,
+    Json,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Options {
+    data: PathBuf,
+    format: Format,
+    registry: bool,
+    state: bool,
+    enrich: Option<Enrich>,
+    /// the addresses, or the files if enrich
+    addrs: Vec<String>,
+}
+
+fn parse_args(args: impl Iterator<Item=String>) -> Result<Option<Options>, String> {
+    let mut opts = Options {
+        data: PathBuf::from("./data"),
+        format: Format::Plain,
+        registry: false,
+        state: false,
+        enrich: None,
+        addrs: Vec::new(),
+    };
+    let mut args = Args::new(args);
+    while let Some(name) = args.next_name() {
+        match name.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-d" | "--data" => opts.data = args.value(&name)?.into(),
+            "-f" | "--format" => opts.format = match args.value(&name)?.as_str() {
+                "plain" => Format::Plain,
+                "tsv" => Format::Tsv,
+                "json" => Format::Json,
+                f => return Err(format!("unknown format: {}", f)),
+            },
+            "--registry" => opts.registry = true,
+            "--state" => opts.state = true,
+            "-e" | "--enrich" => opts.enrich = match args.value(&name)?.as_str() {
+                "inline" => Some(Enrich::Inline),
+                "column" => Some(Enrich::Column),
+                m => return Err(format!("unknown enrich mode: {}", m)),
+            },
+            "-" => opts.addrs.push(name),
+            n if n.starts_with('-') => return Err(format!("unknown option: {}", n)),
+            _ => opts.addrs.push(name),
+        }
+    }
+    Ok(Some(opts))
+}
+
+// all the files of a snapshot directory, like `RecordMap::load_from_dir`, or one delegated file
+fn load(opts: &Options) -> Result<RecordMap, Box<dyn Error>> {
+    let mut map = RecordMap::new();
+    if opts.data.is_dir() {
+        map.load_from_dir(&opts.data)?;
+    } else {
+        map.load_from_file(&opts.data)?;
+    }
+    Ok(map)
+}
+
+fn header(opts: &Options) -> Option<String> {
+    if opts.format != Format::Tsv {
+        return None;
+    }
+    let mut s = String::from("ip\tcode");
+    if opts.registry {
+        s.push_str("\tregistry");
+    }
+    if opts.state {
+        s.push_str("\tstate");
+    }
+    Some(s)
+}
+
+fn format_line(opts: &Options, ip: IpAddr, record: Option<&Record>) -> String {
+    let code = record.map(|r| r.code.name());
+    let registry = record.map(|r| r.registry.as_str());
+    let state = record.map(|r| r.state.as_str());
+    let mut s = String::new();
+    match opts.format {
+        Format::Plain | Format::Tsv => {
+            let (sep, none) = if opts.format == Format::Plain { (' ', "-") } else { ('\t', "") };
+            let _ = write!(s, "{}{}{}", ip, sep, code.unwrap_or(none));
+            if opts.registry {
+                let _ = write!(s, "{}{}", sep, registry.filter(|r| !r.is_empty()).unwrap_or(none));
+            }
+            if opts.state {
+                let _ = write!(s, "{}{}", sep, state.unwrap_or(none));
+            }
+        }
+        Format::Json => {
+            let _ = write!(s, "{{\"ip\":\"{}\",\"code\":{}", ip, json_value(code));
+            if opts.registry {
+                let _ = write!(s, ",\"registry\":{}", json_value(registry));
+            }
+            if opts.state {
+                let _ = write!(s, ",\"state\":{}", json_value(state));
+            }
+            s.push('}');
+        }
+    }
+    s
+}
+
+fn json_value(v: Option<&str>) -> String {
+    v.map_or_else(|| "null".to_string(), json_str)
+}
+
+/// Resolves and prints one address, returns `false` if it is invalid or unmapped.
+fn resolve(opts: &Options, map: &RecordMap, addr: &str, out: &mut impl Write) -> io::Result<bool> {
+    let addr = addr.trim();
+    // accept `[::1]` as well
+    let Ok(ip) = addr.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() else {
+        eprintln!("ip2c: invalid address: {}", addr);
+        return Ok(false);
+    };
+    let record = map.query(ip);
+    writeln!(out, "{}", format_line(opts, ip, record))?;
+    Ok(record.is_some())
+}
+
+fn run(opts: &Options, map: &RecordMap) -> io::Result<bool> {
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    if let Some(h) = header(opts) {
+        writeln!(out, "{}", h)?;
+    }
+    let mut all = true;
+    let stdin_only = [String::from("-")];
+    let addrs = if opts.addrs.is_empty() { &stdin_only[..] } else { &opts.addrs[..] };
+    for addr in addrs {
+        if addr != "-" {
+            all &= resolve(opts, map, addr, &mut out)?;
+            continue;
+        }
+        for line in io::stdin().lock().lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                all &= resolve(opts, map, &line, &mut out)?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(all)
+}
+
+fn main() -> ExitCode {
+    let opts = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("ip2c: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let map = match load(&opts) {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("ip2c: load {} failed: {}", opts.data.display(), e);
+            return ExitCode::from(2);
+        }
+    };
+    let r = match opts.enrich {
+        Some(mode) => enrich::run(mode, &map, &opts.addrs).map(|_| true),
+        None => run(&opts, &map),
+    };
+    match r {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        // eg. `ip2c ... | head`
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("ip2c: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> impl Iterator<Item=String> + '_ {
+        s.split_whitespace().map(String::from)
+    }
+
+    #[test]
+    fn options() {
+        let opts = parse_args(args("-d data/example.txt --format=json --state 10.0.0.1 -")).unwrap().unwrap();
+        assert_eq!(opts.data, PathBuf::from("data/example.txt"));
+        assert_eq!(opts.format, Format::Json);
+        assert!(opts.state && !opts.registry);
+        assert_eq!(opts.addrs, ["10.0.0.1", "-"]);
+        assert_eq!(parse_args(args("--help")), Ok(None));
+        assert!(parse_args(args("-f xml")).is_err());
+        assert!(parse_args(args("--data")).is_err());
+        assert!(parse_args(args("--verbose")).is_err());
+        let opts = parse_args(args("-e column access.log")).unwrap().unwrap();
+        assert_eq!(opts.enrich, Some(Enrich::Column));
+        assert_eq!(opts.addrs, ["access.log"]);
+        assert!(parse_args(args("--enrich=csv")).is_err());
+    }
+
+    #[test]
+    fn snapshot() {
+        let dir = std::env::temp_dir().join(format!("ip2c-cli-snapshot-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("old")).unwrap();
+        std::fs::write(dir.join("delegated-apnic-latest"), "apnic|CN|ipv4|1.0.1.0|256|20110414|allocated\n").unwrap();
+        std::fs::write(dir.join("delegated-ripencc-latest"), "ripencc|NL|ipv4|2.56.8.0|1024|20190212|allocated\n").unwrap();
+        // the subdirectories are skipped
+        std::fs::write(dir.join("old/delegated-arin-latest"), "arin|US|ipv4|8.8.8.0|256|19921201|allocated\n").unwrap();
+
+        let opts = parse_args(args(&format!("-d {}", dir.display()))).unwrap().unwrap();
+        let map = load(&opts).unwrap();
+        assert_eq!(map.query("1.0.1.1".parse().unwrap()).unwrap().code.name(), "CN");
+        assert_eq!(map.query("2.56.9.1".parse().unwrap()).unwrap().code.name(), "NL");
+        assert!(map.query("8.8.8.8".parse().unwrap()).is_none());
+
+        let opts = parse_args(args(&format!("-d {}", dir.join("delegated-apnic-latest").display()))).unwrap().unwrap();
+        let map = load(&opts).unwrap();
+        assert!(map.query("1.0.1.1".parse().unwrap()).is_some() && map.query("2.56.9.1".parse().unwrap()).is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn output() {
+        let mut opts = parse_args(args("-d data --registry --state")).unwrap().unwrap();
+        let map = load(&opts).unwrap();
+        let mut out = Vec::new();
+        assert!(resolve(&opts, &map, "10.1.2.3", &mut out).unwrap());
+        assert!(!resolve(&opts, &map, "8.8.8.8", &mut out).unwrap());
+        assert!(!resolve(&opts, &map, "not-an-ip", &mut out).unwrap());
+        assert_eq!(String::from_utf8(out).unwrap(), "10.1.2.3 -- - unknown\n8.8.8.8 - - -\n");
+
+        opts.format = Format::Tsv;
+        assert_eq!(header(&opts).unwrap(), "ip\tcode\tregistry\tstate");
+        assert_eq!(format_line(&opts, "8.8.8.8".parse().unwrap(), None), "8.8.8.8\t\t\t");
+
+        opts.format = Format::Json;
+        let ip = "127.0.0.1".parse().unwrap();
+        assert_eq!(format_line(&opts, ip, map.query(ip)), r#"{"ip":"127.0.0.1","code":"--","registry":"","state":"unknown"}"#);
+        assert_eq!(format_line(&opts, "::2".parse().unwrap(), None), r#"{"ip":"::2","code":null,"registry":null,"state":null}"#);
+    }
+}
@@ -0,0 +1,276 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::{Interval, IpAddress};
+
+/// A network block in CIDR notation, eg. `10.0.0.0/8`.
+///
+/// The host bits of the address are always zero. Parse with `"10.0.0.0/8".parse()` (strict, host bits are rejected)
+/// or [Cidr::parse_lenient] (host bits are masked like the [Interval] parser does).
+///
+/// ```
+/// use ip2c::{Cidr, Interval, IPv4};
+///
+/// let net: Cidr<IPv4> = "100.0.0.0/20".parse().unwrap();
+/// assert_eq!(net.network(), "100.0.0.0".parse().unwrap());
+/// assert_eq!(net.broadcast(), "100.0.15.255".parse().unwrap());
+/// assert_eq!(net.prefix_len(), 20);
+/// assert!("100.0.0.1/20".parse::<Cidr<IPv4>>().is_err());
+/// assert_eq!(Cidr::<IPv4>::parse_lenient("100.0.0.1/20").unwrap(), net);
+///
+/// let v: Interval<IPv4> = net.into();
+/// assert_eq!(Cidr::try_from(v), Ok(net));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Cidr<T: IpAddress> {
+    addr: T,
+    len: u8,
+}
+
+// the mask of the low `BITS - len` bits
+fn host_mask<T: IpAddress>(len: u8) -> u128 {
+    let host = (T::BITS - len) as u32;
+    if host == 128 { u128::MAX } else { (1 << host) - 1 }
+}
+
+impl<T: IpAddress> Cidr<T> {
+    /// Returns `None` if the prefix length is too large or the host bits of the address are set.
+    pub fn new(addr: T, len: u8) -> Option<Self> {
+        if len > T::BITS || addr.to_bits() & host_mask::<T>(len) != 0 {
+            return None;
+        }
+        Some(Cidr { addr, len })
+    }
+
+    /// Like [Cidr::new], but the host bits of the address are masked.
+    pub fn new_lenient(addr: T, len: u8) -> Option<Self> {
+        if len > T::BITS {
+            return None;
+        }
+        Some(Cidr { addr: T::from_bits(addr.to_bits() & !host_mask::<T>(len)), len })
+    }
+
+    /// the first address
+    pub fn network(&self) -> T {
+        self.addr
+    }
+
+    /// the last address
+    pub fn broadcast(&self) -> T {
+        T::from_bits(self.addr.to_bits() | host_mask::<T>(self.len))
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.len
+    }
+
+    pub fn contains(&self, addr: T) -> bool {
+        self.network() <= addr && addr <= self.broadcast()
+    }
+
+    /// Returns `true` if the other block is the same or a subnet of this one.
+    pub fn contains_cidr(&self, other: &Cidr<T>) -> bool {
+        self.len <= other.len && self.contains(other.addr)
+    }
+
+    /// The block one bit shorter, `None` for `/0`.
+    ///
+    /// ```
+    /// use ip2c::{Cidr, IPv4};
+    ///
+    /// let net: Cidr<IPv4> = "10.1.0.0/16".parse().unwrap();
+    /// assert_eq!(net.supernet(), Some("10.0.0.0/15".parse().unwrap()));
+    /// ```
+    pub fn supernet(&self) -> Option<Self> {
+        if self.len == 0 {
+            return None;
+        }
+        Self::new_lenient(self.addr, self.len - 1)
+    }
+
+    /// Iterate the subnets of the new prefix length, `None` if it is shorter than the current one or too large.
+    ///
+    /// ```
+    /// use ip2c::{Cidr, IPv4};
+    ///
+    /// let net: Cidr<IPv4> = "10.0.0.0/23".parse().unwrap();
+    /// let v: Vec<_> = net.subnets(24).unwrap().map(|n| n.to_string()).collect();
+    /// assert_eq!(v, ["10.0.0.0/24", "10.0.1.0/24"]);
+    /// assert!(net.subnets(22).is_none());
+    /// ```
+    pub fn subnets(&self, new_len: u8) -> Option<Subnets<T>> {
+        if new_len < self.len || new_len > T::BITS {
+            return None;
+        }
+        Some(Subnets {
+            next: Some(self.addr.to_bits()),
+            last: self.broadcast().to_bits(),
+            len: new_len,
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+/// The iterator returned by [Cidr::subnets]
+pub struct Subnets<T: IpAddress> {
+    next: Option<u128>,
+    last: u128,
+    len: u8,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: IpAddress> Iterator for Subnets<T> {
+    type Item = Cidr<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let addr = self.next?;
+        let end = addr | host_mask::<T>(self.len);
+        self.next = if end >= self.last { None } else { Some(end + 1) };
+        Some(Cidr { addr: T::from_bits(addr), len: self.len })
+    }
+}
+
+impl<T: IpAddress + Display> Display for Cidr<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}/{}", self.addr, self.len))
+    }
+}
+
+impl<T: IpAddress> From<Cidr<T>> for Interval<T> {
+    fn from(c: Cidr<T>) -> Self {
+        Interval(c.network(), c.broadcast())
+    }
+}
+
+impl<T: IpAddress> Interval<T> {
+    /// The fewest CIDR blocks which exactly cover the interval, in order.
+    ///
+    /// ```
+    /// use ip2c::{Interval, IPv4};
+    ///
+    /// let v: Interval<IPv4> = "10.0.0.1-10.0.0.6".parse().unwrap();
+    /// let blocks: Vec<_> = v.cidrs().map(|c| c.to_string()).collect();
+    /// assert_eq!(blocks, ["10.0.0.1/32", "10.0.0.2/31", "10.0.0.4/31", "10.0.0.6/32"]);
+    /// ```
+    pub fn cidrs(&self) -> Cidrs<T> {
+        Cidrs {
+            next: if self.0 <= self.1 { Some(self.0.to_bits()) } else { None },
+            last: self.1.to_bits(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+/// The iterator returned by [Interval::cidrs]
+pub struct Cidrs<T: IpAddress> {
+    next: Option<u128>,
+    last: u128,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: IpAddress> Iterator for Cidrs<T> {
+    type Item = Cidr<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let addr = self.next?;
+        // the largest block aligned at `addr` and not beyond `last`
+        let align = addr.trailing_zeros().min(T::BITS as u32);
+        let span = self.last - addr;
+        let fit = if span == u128::MAX { 128 } else { 127 - (span + 1).leading_zeros() };
+        let host = align.min(fit);
+        let end = addr | if host == 128 { u128::MAX } else { (1 << host) - 1 };
+        self.next = if end >= self.last { None } else { Some(end + 1) };
+        Some(Cidr { addr: T::from_bits(addr), len: T::BITS - host as u8 })
+    }
+}
+
+/// The interval can not be expressed by one CIDR block
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct NotCidrError<T: IpAddress>(pub Interval<T>);
+
+impl<T: IpAddress + Display> Display for NotCidrError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("interval {} is not a cidr block", self.0))
+    }
+}
+
+impl<T: IpAddress + Display> Debug for NotCidrError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl<T: IpAddress + Display> Error for NotCidrError<T> {}
+
+impl<T: IpAddress> TryFrom<Interval<T>> for Cidr<T> {
+    type Error = NotCidrError<T>;
+
+    fn try_from(v: Interval<T>) -> Result<Self, Self::Error> {
+        let (start, end) = (v.0.to_bits(), v.1.to_bits());
+        if start > end {
+            return Err(NotCidrError(v));
+        }
+        let host = end - start;
+        // the size must be a power of two and the start aligned to it
+        if host.wrapping_add(1) & host != 0 || start & host != 0 {
+            return Err(NotCidrError(v));
+        }
+        let len = T::BITS - (128 - host.leading_zeros()) as u8;
+        Ok(Cidr { addr: v.0, len })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cidr, Interval, IPv4, IPv6};
+
+    #[test]
+    fn convert() {
+        let all: Cidr<IPv6> = "::/0".parse().unwrap();
+        assert_eq!(Interval::from(all), Interval(IPv6(0), IPv6(u128::MAX)));
+        assert_eq!(Cidr::try_from(Interval(IPv6(0), IPv6(u128::MAX))), Ok(all));
+        let host: Cidr<IPv4> = "1.2.3.4".parse().unwrap();
+        assert_eq!(host.prefix_len(), 32);
+        assert_eq!(Cidr::try_from(Interval::from(IPv4(0x01020304))), Ok(host));
+        assert!(Cidr::try_from(Interval(IPv4(0), IPv4(2))).is_err());
+        assert!(Cidr::try_from(Interval(IPv4(1), IPv4(2))).is_err());
+        assert_eq!(Cidr::try_from(Interval(IPv4(4), IPv4(7))).unwrap().to_string(), "0.0.0.4/30");
+    }
+
+    #[test]
+    fn subnets() {
+        let all: Cidr<IPv4> = "0.0.0.0/0".parse().unwrap();
+        assert_eq!(all.subnets(0).unwrap().count(), 1);
+        assert_eq!(all.subnets(8).unwrap().count(), 256);
+        assert_eq!(all.subnets(8).unwrap().last().unwrap().to_string(), "255.0.0.0/8");
+        let net: Cidr<IPv6> = "2001:db8::/126".parse().unwrap();
+        assert_eq!(net.subnets(128).unwrap().count(), 4);
+        assert!(net.subnets(129).is_none());
+        assert!(net.contains_cidr(&"2001:db8::2/127".parse().unwrap()));
+        assert!(!net.contains_cidr(&"2001:db8::/125".parse().unwrap()));
+        assert_eq!(all.supernet(), None);
+    }
+
+    #[test]
+    fn cidrs() {
+        let all = Interval(IPv6(0), IPv6(u128::MAX));
+        assert_eq!(all.cidrs().map(|c| c.to_string()).collect::<Vec<_>>(), ["::/0"]);
+        let all = Interval(IPv4(0), IPv4(u32::MAX));
+        assert_eq!(all.cidrs().map(|c| c.to_string()).collect::<Vec<_>>(), ["0.0.0.0/0"]);
+        let v = Interval(IPv4(1), IPv4(u32::MAX));
+        assert_eq!(v.cidrs().count(), 32);
+        assert_eq!(v.cidrs().last().unwrap().to_string(), "128.0.0.0/1");
+        let v = Interval(IPv4(u32::MAX), IPv4(u32::MAX));
+        assert_eq!(v.cidrs().collect::<Vec<_>>(), [Cidr::new(IPv4(u32::MAX), 32).unwrap()]);
+        assert_eq!(Interval(IPv4(2), IPv4(1)).cidrs().count(), 0);
+        // every block is covered exactly
+        let v = Interval(IPv6(3), IPv6(1000));
+        let mut next = 3;
+        for c in v.cidrs() {
+            let c = Interval::from(c);
+            assert_eq!(c.0.0, next);
+            next = c.1.0 + 1;
+        }
+        assert_eq!(next, 1001);
+    }
+}
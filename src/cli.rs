@@ -0,0 +1,63 @@
+//! The command line arguments of the `ip2c` and `ip2c-server` binaries.
+
+/// The options and operands, of `--name=value` or `--name value`.
+///
+/// ```
+/// use ip2c::cli::Args;
+///
+/// let mut args = Args::new(["--data=./data", "-f", "json", "1.0.1.1"].into_iter().map(String::from));
+/// assert_eq!(args.next_name().as_deref(), Some("--data"));
+/// assert_eq!(args.value("--data").as_deref(), Ok("./data"));
+/// assert_eq!(args.next_name().as_deref(), Some("-f"));
+/// assert_eq!(args.value("-f").as_deref(), Ok("json"));
+/// assert_eq!(args.next_name().as_deref(), Some("1.0.1.1"));
+/// ```
+pub struct Args<I> {
+    args: I,
+    /// the value after `=` of the current option
+    value: Option<String>,
+}
+
+impl<I: Iterator<Item=String>> Args<I> {
+    pub fn new(args: I) -> Self {
+        Args { args, value: None }
+    }
+
+    /// The next option name or operand.
+    pub fn next_name(&mut self) -> Option<String> {
+        let arg = self.args.next()?;
+        match arg.split_once('=') {
+            Some((n, v)) if n.starts_with("--") => {
+                self.value = Some(v.to_string());
+                Some(n.to_string())
+            }
+            _ => {
+                self.value = None;
+                Some(arg)
+            }
+        }
+    }
+
+    /// The value of the current option `name`.
+    pub fn value(&mut self, name: &str) -> Result<String, String> {
+        self.value.take().or_else(|| self.args.next()).ok_or(format!("missing value of {}", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Args;
+
+    #[test]
+    fn names_and_values() {
+        let mut args = Args::new(["--data=x=y", "-d", "z", "a=b", "--http"].into_iter().map(String::from));
+        assert_eq!(args.next_name().as_deref(), Some("--data"));
+        assert_eq!(args.value("--data").as_deref(), Ok("x=y"));
+        assert_eq!(args.next_name().as_deref(), Some("-d"));
+        assert_eq!(args.value("-d").as_deref(), Ok("z"));
+        assert_eq!(args.next_name().as_deref(), Some("a=b"));
+        assert_eq!(args.next_name().as_deref(), Some("--http"));
+        assert_eq!(args.value("--http"), Err("missing value of --http".to_string()));
+        assert_eq!(args.next_name(), None);
+    }
+}
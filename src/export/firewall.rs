@@ -0,0 +1,137 @@
+use std::fmt::{Display, Write};
+
+use crate::Cidr;
+use crate::export::CidrSet;
+use crate::IpAddress;
+
+impl CidrSet {
+    /// The `ipset restore` script, the sets are `{name}-v4` (`hash:net family inet`) and `{name}-v6`.
+    ///
+    /// The sets are created if not exist and flushed, so the script can be restored again to update them.
+    ///
+    /// ```
+    /// use ip2c::export::CidrSet;
+    ///
+    /// let set = CidrSet { ipv4: vec!["1.0.1.0/24".parse().unwrap()], ipv6: vec![] };
+    /// assert_eq!(set.to_ipset("cn"), "\
+    /// create cn-v4 hash:net family inet maxelem 65536 -exist
+    /// flush cn-v4
+    /// add cn-v4 1.0.1.0/24
+    /// create cn-v6 hash:net family inet6 maxelem 65536 -exist
+    /// flush cn-v6
+    /// ");
+    /// ```
+    pub fn to_ipset(&self, name: &str) -> String {
+        let mut s = String::new();
+        write_ipset(&mut s, &format!("{}-v4", name), "inet", &self.ipv4);
+        write_ipset(&mut s, &format!("{}-v6", name), "inet6", &self.ipv6);
+        s
+    }
+
+    /// The nftables `set`s with the interval flag in a table, load by `nft -f`.
+    /// The sets are `{name}_v4` and `{name}_v6`.
+    ///
+    /// ```
+    /// use ip2c::export::CidrSet;
+    ///
+    /// let set = CidrSet { ipv4: vec!["1.0.1.0/24".parse().unwrap(), "1.0.2.0/23".parse().unwrap()], ipv6: vec![] };
+    /// assert_eq!(set.to_nftables("inet filter", "cn"), "\
+    /// table inet filter {
+    /// \tset cn_v4 {
+    /// \t\ttype ipv4_addr
+    /// \t\tflags interval
+    /// \t\telements = { 1.0.1.0/24, 1.0.2.0/23 }
+    /// \t}
+    /// \tset cn_v6 {
+    /// \t\ttype ipv6_addr
+    /// \t\tflags interval
+    /// \t}
+    /// }
+    /// ");
+    /// ```
+    pub fn to_nftables(&self, table: &str, name: &str) -> String {
+        let mut s = String::new();
+        let _ = writeln!(s, "table {} {{", table);
+        write_nft_set(&mut s, &format!("{}_v4", name), "ipv4_addr", &self.ipv4);
+        write_nft_set(&mut s, &format!("{}_v6", name), "ipv6_addr", &self.ipv6);
+        s.push_str("}\n");
+        s
+    }
+
+    /// The `iptables` and `ip6tables` commands appending a rule of every block to the chain.
+    ///
+    /// ```
+    /// use ip2c::export::CidrSet;
+    ///
+    /// let set = CidrSet { ipv4: vec!["1.0.1.0/24".parse().unwrap()], ipv6: vec!["2001:250::/35".parse().unwrap()] };
+    /// assert_eq!(set.to_iptables("INPUT", "DROP"), "\
+    /// iptables -A INPUT -s 1.0.1.0/24 -j DROP
+    /// ip6tables -A INPUT -s 2001:250::/35 -j DROP
+    /// ");
+    /// ```
+    pub fn to_iptables(&self, chain: &str, target: &str) -> String {
+        let mut s = String::new();
+        for c in &self.ipv4 {
+            let _ = writeln!(s, "iptables -A {} -s {} -j {}", chain, c, target);
+        }
+        for c in &self.ipv6 {
+            let _ = writeln!(s, "ip6tables -A {} -s {} -j {}", chain, c, target);
+        }
+        s
+    }
+}
+
+// the default maxelem of ipset
+const IPSET_MAXELEM: usize = 65536;
+
+fn write_ipset<T: IpAddress + Display>(s: &mut String, name: &str, family: &str, list: &[Cidr<T>]) {
+    let _ = writeln!(s, "create {} hash:net family {} maxelem {} -exist", name, family, list.len().max(IPSET_MAXELEM));
+    let _ = writeln!(s, "flush {}", name);
+    for c in list {
+        let _ = writeln!(s, "add {} {}", name, c);
+    }
+}
+
+fn write_nft_set<T: IpAddress + Display>(s: &mut String, name: &str, ty: &str, list: &[Cidr<T>]) {
+    let _ = writeln!(s, "\tset {} {{", name);
+    let _ = writeln!(s, "\t\ttype {}", ty);
+    s.push_str("\t\tflags interval\n");
+    // nft rejects the empty `elements = { }`
+    if !list.is_empty() {
+        s.push_str("\t\telements = { ");
+        for (i, c) in list.iter().enumerate() {
+            if i > 0 {
+                s.push_str(", ");
+            }
+            let _ = write!(s, "{}", c);
+        }
+        s.push_str(" }\n");
+    }
+    s.push_str("\t}\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::export::CidrSet;
+    use crate::rir::{CountryRegionCode, IpCodeMap};
+
+    #[test]
+    fn select() {
+        let cn = CountryRegionCode::new("CN").unwrap();
+        let hk = CountryRegionCode::new("HK").unwrap();
+        let us = CountryRegionCode::new("US").unwrap();
+        let mut map = IpCodeMap::new();
+        map.ipv4.insert("1.0.0.0-1.0.0.127".parse().unwrap(), cn).unwrap();
+        map.ipv4.insert("1.0.0.128-1.0.0.255".parse().unwrap(), hk).unwrap();
+        map.ipv4.insert("1.0.1.0-1.0.1.9".parse().unwrap(), cn).unwrap();
+        map.ipv4.insert("1.0.1.10-1.0.1.255".parse().unwrap(), us).unwrap();
+        map.ipv4.insert("1.0.3.0/24".parse().unwrap(), cn).unwrap();
+
+        let set = CidrSet::select(&map, &[cn, hk]);
+        // the adjacent ranges are merged before the decomposition
+        assert_eq!(set.to_string(), "1.0.0.0/24\n1.0.1.0/29\n1.0.1.8/31\n1.0.3.0/24\n");
+        assert_eq!(set.ipv4.len(), 4);
+        assert!(CidrSet::select(&map, &[]).is_empty());
+        assert_eq!(CidrSet::select(&map, &[us]).to_iptables("OUTPUT", "REJECT").lines().count(), 6);
+    }
+}
@@ -0,0 +1,90 @@
+//! Export the address blocks of selected countries and regions for firewalls, see [CidrSet],
+//! and the country mapping of proxies, see [to_nginx_geo], [to_haproxy_map] and [to_apache].
+
+mod firewall;
+mod proxy;
+
+pub use proxy::*;
+
+use std::fmt::{Display, Formatter};
+
+use crate::{Cidr, Discrete, Interval, IntervalTreeMap, IpAddress, IpTree, IPv4, IPv6};
+use crate::rir::{CountryRegionCode, IpCodeMap};
+
+/// The merged and minimal CIDR blocks of some ranges.
+///
+/// The `Display` is the plain list, one block a line.
+///
+/// ```
+/// use ip2c::export::CidrSet;
+/// use ip2c::rir::{CountryRegionCode, IpCodeMap};
+///
+/// let cn = CountryRegionCode::new("CN").unwrap();
+/// let jp = CountryRegionCode::new("JP").unwrap();
+/// let mut map = IpCodeMap::new();
+/// map.ipv4.insert("1.0.1.0/24".parse().unwrap(), cn).unwrap();
+/// map.ipv4.insert("1.0.2.0-1.0.3.255".parse().unwrap(), cn).unwrap();
+/// map.ipv4.insert("1.0.16.0/20".parse().unwrap(), jp).unwrap();
+/// map.ipv6.insert("2001:250::/35".parse().unwrap(), cn).unwrap();
+///
+/// let set = CidrSet::select(&map, &[cn]);
+/// assert_eq!(set.to_string(), "1.0.1.0/24\n1.0.2.0/23\n2001:250::/35\n");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct CidrSet {
+    pub ipv4: Vec<Cidr<IPv4>>,
+    pub ipv6: Vec<Cidr<IPv6>>,
+}
+
+impl CidrSet {
+    /// The blocks of the codes.
+    pub fn select(map: &IpCodeMap, codes: &[CountryRegionCode]) -> Self {
+        Self::from_tree(map, |code| codes.contains(code))
+    }
+
+    /// The blocks of the values which the predicate returns `true`.
+    pub fn from_tree<V>(map: &IpTree<V>, f: impl Fn(&V) -> bool) -> Self {
+        CidrSet {
+            ipv4: to_cidrs(&map.ipv4, &f),
+            ipv6: to_cidrs(&map.ipv6, &f),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ipv4.is_empty() && self.ipv6.is_empty()
+    }
+}
+
+// merge the adjacent ranges, then decompose
+fn to_cidrs<K, V>(tree: &IntervalTreeMap<K, V>, f: &impl Fn(&V) -> bool) -> Vec<Cidr<K>>
+    where K: Discrete + IpAddress {
+    merge_by(tree, |v| f(v).then_some(())).iter().flat_map(|(k, _)| k.cidrs()).collect()
+}
+
+// the ranges which `f` returns `Some`, the adjacent ranges of the same result are merged
+fn merge_by<K, V, T>(tree: &IntervalTreeMap<K, V>, f: impl Fn(&V) -> Option<T>) -> Vec<(Interval<K>, T)>
+    where K: Discrete, T: PartialEq {
+    let mut merged: Vec<(Interval<K>, T)> = Vec::new();
+    for (k, v) in tree.iter() {
+        let Some(t) = f(v) else {
+            continue;
+        };
+        match merged.last_mut() {
+            Some((last, x)) if *x == t && last.is_adjacent(k) => last.1 = k.1,
+            _ => merged.push((*k, t)),
+        }
+    }
+    merged
+}
+
+impl Display for CidrSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for c in &self.ipv4 {
+            writeln!(f, "{}", c)?;
+        }
+        for c in &self.ipv6 {
+            writeln!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt::{Display, Write};
+
+use crate::{Cidr, Discrete, IntervalTreeMap, IpAddress};
+use crate::export::merge_by;
+use crate::rir::{CountryRegionCode, IpCodeMap};
+
+/// The options of the proxy exporters
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProxyOptions {
+    /// the value of the addresses not in the map, eg. `ZZ`
+    pub default: Option<String>,
+    /// merge the adjacent ranges of the same code, so there are fewer blocks
+    pub coalesce: bool,
+}
+
+fn tree_blocks<K>(tree: &IntervalTreeMap<K, CountryRegionCode>, coalesce: bool) -> Vec<(Cidr<K>, CountryRegionCode)>
+    where K: Discrete + IpAddress {
+    let ranges = if coalesce {
+        merge_by(tree, |code| Some(*code))
+    } else {
+        tree.iter().map(|(k, code)| (*k, *code)).collect()
+    };
+    ranges.iter().flat_map(|(k, code)| k.cidrs().map(|c| (c, *code))).collect()
+}
+
+// the blocks of every range, ipv4 first
+fn for_each_block(map: &IpCodeMap, coalesce: bool, mut f: impl FnMut(&dyn Display, CountryRegionCode)) {
+    for (c, code) in &tree_blocks(&map.ipv4, coalesce) {
+        f(c, *code);
+    }
+    for (c, code) in &tree_blocks(&map.ipv6, coalesce) {
+        f(c, *code);
+    }
+}
+
+/// The nginx `geo` block of the variable, eg. `$country`.
+///
+/// ```
+/// use ip2c::export::{to_nginx_geo, ProxyOptions};
+/// use ip2c::rir::{CountryRegionCode, IpCodeMap};
+///
+/// let cn = CountryRegionCode::new("CN").unwrap();
+/// let mut map = IpCodeMap::new();
+/// map.ipv4.insert("1.0.1.0/24".parse().unwrap(), cn).unwrap();
+/// map.ipv4.insert("1.0.2.0-1.0.3.255".parse().unwrap(), cn).unwrap();
+/// map.ipv6.insert("2001:250::/35".parse().unwrap(), cn).unwrap();
+///
+/// let opts = ProxyOptions { default: Some("ZZ".to_string()), coalesce: false };
+/// assert_eq!(to_nginx_geo(&map, "$country", &opts), "\
+/// geo $country {
+///     default ZZ;
+///     1.0.1.0/24 CN;
+///     1.0.2.0/23 CN;
+///     2001:250::/35 CN;
+/// }
+/// ");
+/// ```
+pub fn to_nginx_geo(map: &IpCodeMap, variable: &str, opts: &ProxyOptions) -> String {
+    let mut s = String::new();
+    let _ = writeln!(s, "geo {} {{", variable);
+    if let Some(default) = &opts.default {
+        let _ = writeln!(s, "    default {};", default);
+    }
+    for_each_block(map, opts.coalesce, |c, code| {
+        let _ = writeln!(s, "    {} {};", c, code);
+    });
+    s.push_str("}\n");
+    s
+}
+
+/// The HAProxy map file of `cidr code` lines, used by `map_ip`, eg. `%[src,map_ip(/etc/haproxy/country.map)]`.
+///
+/// The default value is mapped by `0.0.0.0/0` and `::/0` as the map is matched by the longest prefix.
+///
+/// ```
+/// use ip2c::export::{to_haproxy_map, ProxyOptions};
+/// use ip2c::rir::{CountryRegionCode, IpCodeMap};
+///
+/// let cn = CountryRegionCode::new("CN").unwrap();
+/// let mut map = IpCodeMap::new();
+/// map.ipv4.insert("1.0.1.0/24".parse().unwrap(), cn).unwrap();
+/// map.ipv4.insert("1.0.2.0-1.0.3.255".parse().unwrap(), cn).unwrap();
+///
+/// let opts = ProxyOptions { default: None, coalesce: true };
+/// assert_eq!(to_haproxy_map(&map, &opts), "1.0.1.0/24 CN\n1.0.2.0/23 CN\n");
+/// ```
+pub fn to_haproxy_map(map: &IpCodeMap, opts: &ProxyOptions) -> String {
+    let mut s = String::new();
+    for_each_block(map, opts.coalesce, |c, code| {
+        let _ = writeln!(s, "{} {}", c, code);
+    });
+    if let Some(default) = &opts.default {
+        let _ = writeln!(s, "0.0.0.0/0 {}\n::/0 {}", default, default);
+    }
+    s
+}
+
+/// The Apache include which sets the environment variable, eg. `COUNTRY`, by the client address.
+///
+/// The client address is `REMOTE_ADDR`, which is replaced by `mod_remoteip` behind a proxy.
+/// The default is set first and overridden by the later matched block.
+///
+/// ```
+/// use ip2c::export::{to_apache, ProxyOptions};
+/// use ip2c::rir::{CountryRegionCode, IpCodeMap};
+///
+/// let cn = CountryRegionCode::new("CN").unwrap();
+/// let mut map = IpCodeMap::new();
+/// map.ipv6.insert("2001:250::/35".parse().unwrap(), cn).unwrap();
+///
+/// let opts = ProxyOptions { default: Some("ZZ".to_string()), coalesce: true };
+/// assert_eq!(to_apache(&map, "COUNTRY", &opts), "\
+/// SetEnvIfExpr \"true\" COUNTRY=ZZ
+/// SetEnvIfExpr \"-R '2001:250::/35'\" COUNTRY=CN
+/// ");
+/// ```
+pub fn to_apache(map: &IpCodeMap, variable: &str, opts: &ProxyOptions) -> String {
+    let mut s = String::new();
+    if let Some(default) = &opts.default {
+        let _ = writeln!(s, "SetEnvIfExpr \"true\" {}={}", variable, default);
+    }
+    for_each_block(map, opts.coalesce, |c, code| {
+        let _ = writeln!(s, "SetEnvIfExpr \"-R '{}'\" {}={}", c, variable, code);
+    });
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::export::{to_haproxy_map, to_nginx_geo, ProxyOptions};
+    use crate::rir::{CountryRegionCode, IpCodeMap};
+
+    #[test]
+    fn coalesce() {
+        let cn = CountryRegionCode::new("CN").unwrap();
+        let jp = CountryRegionCode::new("JP").unwrap();
+        let mut map = IpCodeMap::new();
+        map.ipv4.insert("1.0.0.0/25".parse().unwrap(), cn).unwrap();
+        map.ipv4.insert("1.0.0.128/25".parse().unwrap(), cn).unwrap();
+        map.ipv4.insert("1.0.1.0/24".parse().unwrap(), jp).unwrap();
+        map.ipv4.insert("1.0.2.0/24".parse().unwrap(), jp).unwrap();
+
+        let mut opts = ProxyOptions::default();
+        assert_eq!(to_haproxy_map(&map, &opts).lines().count(), 4);
+        opts.coalesce = true;
+        // 1.0.1.0-1.0.2.255 is not aligned, so it is still 2 blocks
+        assert_eq!(to_haproxy_map(&map, &opts), "1.0.0.0/24 CN\n1.0.1.0/24 JP\n1.0.2.0/24 JP\n");
+        opts.default = Some("--".to_string());
+        assert!(to_haproxy_map(&map, &opts).ends_with("0.0.0.0/0 --\n::/0 --\n"));
+        assert_eq!(to_nginx_geo(&IpCodeMap::new(), "$cc", &ProxyOptions::default()), "geo $cc {\n}\n");
+    }
+}
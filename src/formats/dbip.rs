@@ -0,0 +1,60 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::net::IpAddr;
+use std::path::Path;
+
+use crate::formats::{for_each_line, insert_range, parse_code, split_csv, Format, ImportError, ImportErrorKind};
+use crate::rir::IpCodeMap;
+
+const FORMAT: Format = Format::DbIpCountryLite;
+
+impl IpCodeMap {
+    /// Loads the DB-IP IP to Country Lite CSV file, eg. `dbip-country-lite-2024-01.csv`.
+    ///
+    /// Returns the number of the ranges inserted.
+    pub fn load_dbip_country(&mut self, file_path: impl AsRef<Path>) -> Result<usize, ImportError> {
+        let path = file_path.as_ref();
+        let file = File::open(path).map_err(|e| ImportError::new(FORMAT, 0, ImportErrorKind::Io(e)).with_path(path))?;
+        self.read_dbip_country(BufReader::new(file)).map_err(|e| e.with_path(path))
+    }
+
+    /// Reads the `ip_start,ip_end,country` lines of DB-IP IP to Country Lite, IPv4 and IPv6 mixed.
+    ///
+    /// The unknown country is `ZZ` in the file, which is kept as is.
+    ///
+    /// ```
+    /// use ip2c::rir::IpCodeMap;
+    ///
+    /// let csv = "1.0.0.0,1.0.0.255,AU\n1.0.1.0,1.0.3.255,CN\n2001:200::,2001:200:ffff:ffff:ffff:ffff:ffff:ffff,JP\n";
+    /// let mut map = IpCodeMap::new();
+    /// assert_eq!(map.read_dbip_country(csv.as_bytes()).unwrap(), 3);
+    /// assert_eq!(map.query("1.0.2.3".parse().unwrap()).unwrap().name(), "CN");
+    /// assert_eq!(map.query("2001:200::1".parse().unwrap()).unwrap().name(), "JP");
+    /// ```
+    pub fn read_dbip_country(&mut self, r: impl BufRead) -> Result<usize, ImportError> {
+        let mut n = 0;
+        for_each_line(FORMAT, r, |_, line| {
+            let fields = split_csv(line);
+            let [start, end, code] = &fields[..] else {
+                return Err(ImportErrorKind::BadColumns(format!("{} columns", fields.len())));
+            };
+            let parse = |s: &str| s.parse::<IpAddr>().map_err(|_| ImportErrorKind::BadAddress(s.to_string()));
+            insert_range(self, parse(start)?, parse(end)?, parse_code(code)?)?;
+            n += 1;
+            Ok(())
+        })?;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rir::IpCodeMap;
+
+    #[test]
+    fn quoted() {
+        let mut map = IpCodeMap::new();
+        assert_eq!(map.read_dbip_country("\"0.0.0.0\",\"0.255.255.255\",\"ZZ\"\r\n".as_bytes()).unwrap(), 1);
+        assert_eq!(map.query("0.1.2.3".parse().unwrap()).unwrap().name(), "ZZ");
+    }
+}
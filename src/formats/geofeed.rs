@@ -0,0 +1,227 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::net::IpAddr;
+use std::path::Path;
+
+use crate::{Cidr, Discrete, Interval, IntervalTreeMap, IpTree, IPv4, IPv6};
+use crate::formats::{for_each_line, insert_network, parse_code, split_csv, Format, ImportError, ImportErrorKind};
+use crate::rir::{CountryRegionCode, IpCodeMap};
+
+const FORMAT: Format = Format::Geofeed;
+
+/// The location of a geofeed prefix, the empty fields are `None`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeofeedEntry {
+    pub code: Option<CountryRegionCode>,
+    /// the ISO 3166-2 code, eg. `US-CA`
+    pub region: Option<String>,
+    pub city: Option<String>,
+    /// deprecated by RFC 8805, but still published by some feeds
+    pub postal: Option<String>,
+}
+
+/// The geofeed (RFC 8805) of `prefix,country,region,city,postal` lines.
+///
+/// ```
+/// use ip2c::formats::GeofeedMap;
+///
+/// let feed = "\
+/// ## self-published geofeed
+/// 192.0.2.0/25,US,US-CA,San Francisco,
+/// 192.0.2.128/25,us,us-wa,Seattle
+/// 2001:db8::/32,DE,,,
+/// ";
+/// let mut map = GeofeedMap::new();
+/// assert_eq!(map.read_geofeed(feed.as_bytes()).unwrap(), 3);
+/// let entry = map.query("192.0.2.200".parse().unwrap()).unwrap();
+/// assert_eq!(entry.region.as_deref(), Some("US-WA"));
+/// assert_eq!(entry.city.as_deref(), Some("Seattle"));
+/// assert_eq!(map.query("2001:db8::1".parse().unwrap()).unwrap().code.unwrap().name(), "DE");
+/// ```
+pub type GeofeedMap = IpTree<GeofeedEntry>;
+
+// the region is `{country}-{1 to 3 letters or digits}`
+fn parse_region(region: &str, code: Option<CountryRegionCode>) -> Result<String, ImportErrorKind> {
+    let region = region.to_ascii_uppercase();
+    let valid = match (region.split_once('-'), code) {
+        (Some((country, sub)), Some(code)) => country == code.name()
+            && (1..=3).contains(&sub.len())
+            && sub.bytes().all(|b| b.is_ascii_alphanumeric()),
+        _ => false,
+    };
+    if valid { Ok(region) } else { Err(ImportErrorKind::BadRegion(region)) }
+}
+
+fn parse_entry(line: &str, feed: &mut GeofeedMap) -> Result<(), ImportErrorKind> {
+    let fields = split_csv(line);
+    if fields.len() > 5 {
+        return Err(ImportErrorKind::BadColumns(format!("{} columns", fields.len())));
+    }
+    let field = |i: usize| fields.get(i).map(|f| f.trim()).filter(|f| !f.is_empty());
+    let prefix = field(0).unwrap_or_default();
+    // a prefix, not a single address, without the host bits
+    let strict = if prefix.contains(':') {
+        prefix.parse::<Cidr<IPv6>>().is_ok()
+    } else {
+        prefix.parse::<Cidr<IPv4>>().is_ok()
+    };
+    if !prefix.contains('/') || !strict {
+        return Err(ImportErrorKind::BadAddress(prefix.to_string()));
+    }
+    let code = field(1).map(|c| parse_code(&c.to_ascii_uppercase())).transpose()?;
+    let region = field(2).map(|r| parse_region(r, code)).transpose()?;
+    let entry = GeofeedEntry {
+        code,
+        region,
+        city: field(3).map(String::from),
+        postal: field(4).map(String::from),
+    };
+    insert_network(feed, prefix, entry)
+}
+
+impl GeofeedMap {
+    /// query [GeofeedEntry] of ip
+    pub fn query(&self, ip: IpAddr) -> Option<&GeofeedEntry> {
+        match ip {
+            IpAddr::V4(ip) => self.ipv4.query(ip.into()),
+            IpAddr::V6(ip) => self.ipv6.query(ip.into()),
+        }
+    }
+
+    /// Loads the geofeed file, returns the number of the prefixes inserted.
+    pub fn load_geofeed(&mut self, file_path: impl AsRef<Path>) -> Result<usize, ImportError> {
+        let path = file_path.as_ref();
+        let file = File::open(path).map_err(|e| ImportError::new(FORMAT, 0, ImportErrorKind::Io(e)).with_path(path))?;
+        self.read_geofeed(BufReader::new(file)).map_err(|e| e.with_path(path))
+    }
+
+    /// Reads the geofeed, the `#` comments and the empty lines are skipped.
+    ///
+    /// The codes are case-insensitive and stored in upper case. The region must be an ISO 3166-2 code
+    /// of the country, which is checked by the form `{country}-{subdivision}` only. The prefixes may not set the host bits, nor overlap.
+    pub fn read_geofeed(&mut self, r: impl BufRead) -> Result<usize, ImportError> {
+        let mut n = 0;
+        for_each_line(FORMAT, r, |_, line| {
+            if line.trim_start().starts_with('#') || line.trim().is_empty() {
+                return Ok(());
+            }
+            parse_entry(line, self)?;
+            n += 1;
+            Ok(())
+        })?;
+        Ok(n)
+    }
+}
+
+// overrides the prefixes strictly inside one range of the tree, as the tree was before any override
+fn overlay_tree<K: Discrete>(tree: &mut IntervalTreeMap<K, CountryRegionCode>, feed: &IntervalTreeMap<K, GeofeedEntry>) -> usize {
+    let applied: Vec<(Interval<K>, CountryRegionCode)> = feed.iter()
+        .filter_map(|(k, entry)| {
+            let code = entry.code?;
+            let (range, _) = tree.get_key_value(k.0)?;
+            (range.contains_interval(k) && range != k).then_some((*k, code))
+        })
+        .collect();
+    for (k, code) in &applied {
+        tree.remove_range(*k);
+        // the span is just cleared
+        let _ = tree.insert(*k, *code);
+    }
+    applied.len()
+}
+
+impl IpCodeMap {
+    /// Overrides the countries by the geofeed, where the prefix is more specific than the delegated range.
+    ///
+    /// The prefixes of the same range as, crossing or outside the delegated ranges are ignored, so the feed
+    /// refines the country of the addresses, but does not claim them. All the prefixes are matched against the delegated
+    /// ranges as they were before the overlay, so the sibling prefixes may cover a whole range. Returns the number of the prefixes applied.
+    ///
+    /// ```
+    /// use ip2c::formats::GeofeedMap;
+    /// use ip2c::rir::{CountryRegionCode, IpCodeMap};
+    ///
+    /// let mut map = IpCodeMap::new();
+    /// map.ipv4.insert("192.0.2.0/24".parse().unwrap(), CountryRegionCode::new("US").unwrap()).unwrap();
+    ///
+    /// let mut feed = GeofeedMap::new();
+    /// feed.read_geofeed("192.0.2.64/26,DE,DE-BE,Berlin,\n198.51.100.0/24,FR,,,\n".as_bytes()).unwrap();
+    /// assert_eq!(map.overlay_geofeed(&feed), 1);
+    /// assert_eq!(map.query("192.0.2.100".parse().unwrap()).unwrap().name(), "DE");
+    /// assert_eq!(map.query("192.0.2.1".parse().unwrap()).unwrap().name(), "US");
+    /// assert_eq!(map.query("198.51.100.1".parse().unwrap()), None);
+    /// ```
+    pub fn overlay_geofeed(&mut self, feed: &GeofeedMap) -> usize {
+        overlay_tree(&mut self.ipv4, &feed.ipv4) + overlay_tree(&mut self.ipv6, &feed.ipv6)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formats::{GeofeedMap, ImportErrorKind};
+    use crate::rir::{CountryRegionCode, IpCodeMap};
+
+    fn read(s: &str) -> Result<GeofeedMap, ImportErrorKind> {
+        let mut feed = GeofeedMap::new();
+        feed.read_geofeed(s.as_bytes()).map(|_| feed).map_err(|e| e.kind)
+    }
+
+    #[test]
+    fn regions() {
+        assert!(read("192.0.2.0/24,GB,GB-ENG,London,\n").is_ok());
+        assert!(read("192.0.2.0/24,FR,FR-75C,,\n").is_ok());
+        assert!(matches!(read("192.0.2.0/24,GB,US-CA,,\n"), Err(ImportErrorKind::BadRegion(r)) if r == "US-CA"));
+        assert!(matches!(read("192.0.2.0/24,GB,ENG,,\n"), Err(ImportErrorKind::BadRegion(_))));
+        assert!(matches!(read("192.0.2.0/24,GB,GB-ENGL,,\n"), Err(ImportErrorKind::BadRegion(_))));
+        assert!(matches!(read("192.0.2.0/24,,GB-ENG,,\n"), Err(ImportErrorKind::BadRegion(_))));
+        assert!(matches!(read("192.0.2.0/24,GBR,,,\n"), Err(ImportErrorKind::BadCode(_))));
+        assert!(matches!(read("192.0.2.1,GB,,,\n"), Err(ImportErrorKind::BadAddress(_))));
+        assert!(matches!(read("192.0.2.1/24,GB,,,\n"), Err(ImportErrorKind::BadAddress(a)) if a == "192.0.2.1/24"));
+        assert!(matches!(read("2001:db8::1/32,GB,,,\n"), Err(ImportErrorKind::BadAddress(_))));
+        assert!(matches!(read("192.0.2.0/33,GB,,,\n"), Err(ImportErrorKind::BadAddress(_))));
+        assert!(matches!(read("192.0.2.0/24,GB,,,,\n"), Err(ImportErrorKind::BadColumns(_))));
+        assert!(matches!(read("192.0.2.0/24,GB\n192.0.2.0/25,GB\n"), Err(ImportErrorKind::Overlap(_))));
+        let feed = read("  # comment\n\n2001:db8::/48,\n").unwrap();
+        assert_eq!(feed.query("2001:db8::1".parse().unwrap()).unwrap().code, None);
+    }
+
+    #[test]
+    fn overlay() {
+        let us = CountryRegionCode::new("US").unwrap();
+        let mut map = IpCodeMap::new();
+        map.ipv4.insert("192.0.2.0/24".parse().unwrap(), us).unwrap();
+        map.ipv4.insert("198.51.100.0/24".parse().unwrap(), us).unwrap();
+        map.ipv6.insert("2001:db8::/32".parse().unwrap(), us).unwrap();
+        let feed = read("\
+192.0.2.0/26,CA,,,
+192.0.2.128/25,MX,,,
+198.51.100.0/24,GB,,,
+198.51.101.0/24,GB,,,
+2001:db8:1::/48,JP,JP-13,Tokyo,
+2001:db8:2::/48,,,,
+").unwrap();
+        assert_eq!(map.overlay_geofeed(&feed), 3);
+        let code = |ip: &str| map.query(ip.parse().unwrap()).map(|c| c.name().to_string());
+        assert_eq!(code("192.0.2.1").as_deref(), Some("CA"));
+        assert_eq!(code("192.0.2.64").as_deref(), Some("US"));
+        assert_eq!(code("192.0.2.255").as_deref(), Some("MX"));
+        // the same range or the undelegated prefix does not override
+        assert_eq!(code("198.51.100.1").as_deref(), Some("US"));
+        assert_eq!(code("198.51.101.1"), None);
+        assert_eq!(code("2001:db8:1::1").as_deref(), Some("JP"));
+        assert_eq!(code("2001:db8:2::1").as_deref(), Some("US"));
+        assert_eq!(map.ipv4.len(), 4);
+    }
+
+    #[test]
+    fn overlay_siblings() {
+        let mut map = IpCodeMap::new();
+        map.ipv4.insert("192.0.2.0/24".parse().unwrap(), CountryRegionCode::new("US").unwrap()).unwrap();
+        // the halves cover the whole range, each is more specific than the delegated /24
+        let feed = read("192.0.2.0/25,CA,,,\n192.0.2.128/25,MX,,,\n").unwrap();
+        assert_eq!(map.overlay_geofeed(&feed), 2);
+        assert_eq!(map.query("192.0.2.1".parse().unwrap()).unwrap().name(), "CA");
+        assert_eq!(map.query("192.0.2.200".parse().unwrap()).unwrap().name(), "MX");
+        assert_eq!(map.ipv4.len(), 2);
+    }
+}
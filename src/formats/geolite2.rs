@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::formats::{for_each_line, insert_network, parse_code, split_csv, Format, ImportError, ImportErrorKind};
+use crate::rir::{CountryRegionCode, IpCodeMap};
+
+const FORMAT: Format = Format::GeoLite2Country;
+const LOCATIONS: &str = "GeoLite2-Country-Locations-en.csv";
+const BLOCKS: [&str; 2] = ["GeoLite2-Country-Blocks-IPv4.csv", "GeoLite2-Country-Blocks-IPv6.csv"];
+
+// geoname id -> country, the continents have no country
+type Locations = HashMap<String, Option<CountryRegionCode>>;
+
+// the indexes of the named columns in the header
+fn columns(header: &str, names: &[&str]) -> Result<Vec<usize>, ImportErrorKind> {
+    let header = split_csv(header);
+    names.iter()
+        .map(|n| header.iter().position(|h| h == n).ok_or_else(|| ImportErrorKind::BadColumns(format!("no {} column", n))))
+        .collect()
+}
+
+fn field(fields: &[String], i: usize) -> Result<&str, ImportErrorKind> {
+    fields.get(i).map(|f| f.as_str()).ok_or_else(|| ImportErrorKind::BadColumns(format!("{} columns", fields.len())))
+}
+
+fn read_locations(r: impl BufRead) -> Result<Locations, ImportError> {
+    let mut locations = Locations::new();
+    let mut cols = None;
+    for_each_line(FORMAT, r, |_, line| {
+        let Some(cols) = &cols else {
+            cols = Some(columns(line, &["geoname_id", "country_iso_code"])?);
+            return Ok(());
+        };
+        let fields = split_csv(line);
+        let id = field(&fields, cols[0])?;
+        let code = match field(&fields, cols[1])? {
+            "" => None,
+            code => Some(parse_code(code)?),
+        };
+        locations.insert(id.to_string(), code);
+        Ok(())
+    })?;
+    Ok(locations)
+}
+
+fn read_blocks(map: &mut IpCodeMap, locations: &Locations, r: impl BufRead) -> Result<usize, ImportError> {
+    let mut n = 0;
+    let mut cols = None;
+    for_each_line(FORMAT, r, |_, line| {
+        let Some(cols) = &cols else {
+            cols = Some(columns(line, &["network", "geoname_id", "registered_country_geoname_id"])?);
+            return Ok(());
+        };
+        let fields = split_csv(line);
+        let network = field(&fields, cols[0])?;
+        // the country of the location, or the registered country, eg. of the anycast networks
+        let id = match field(&fields, cols[1])? {
+            "" => field(&fields, cols[2])?,
+            id => id,
+        };
+        if id.is_empty() {
+            // eg. the anonymous proxies and satellite providers
+            return Ok(());
+        }
+        let code = locations.get(id).ok_or_else(|| ImportErrorKind::UnknownGeonameId(id.to_string()))?;
+        if let Some(code) = code {
+            insert_network(map, network, *code)?;
+            n += 1;
+        }
+        Ok(())
+    })?;
+    Ok(n)
+}
+
+impl IpCodeMap {
+    /// Loads the GeoLite2 Country CSV database, `GeoLite2-Country-Locations-en.csv` and
+    /// `GeoLite2-Country-Blocks-IPv4.csv`/`GeoLite2-Country-Blocks-IPv6.csv`, from the directory.
+    ///
+    /// Returns the number of the networks inserted.
+    pub fn load_geolite2_country(&mut self, dir_path: impl AsRef<Path>) -> Result<usize, ImportError> {
+        let dir = dir_path.as_ref();
+        let open = |name: &str| {
+            let path = dir.join(name);
+            File::open(&path).map(BufReader::new)
+                .map_err(|e| ImportError::new(FORMAT, 0, ImportErrorKind::Io(e)).with_path(&path))
+        };
+        let locations = read_locations(open(LOCATIONS)?).map_err(|e| e.with_path(dir.join(LOCATIONS)))?;
+        let mut n = 0;
+        let mut found = false;
+        for name in BLOCKS {
+            if !dir.join(name).exists() {
+                continue;
+            }
+            found = true;
+            n += read_blocks(self, &locations, open(name)?).map_err(|e| e.with_path(dir.join(name)))?;
+        }
+        if !found {
+            let e = io::Error::new(io::ErrorKind::NotFound, "no GeoLite2-Country-Blocks-IPv4.csv or GeoLite2-Country-Blocks-IPv6.csv");
+            return Err(ImportError::new(FORMAT, 0, ImportErrorKind::Io(e)).with_path(dir));
+        }
+        Ok(n)
+    }
+
+    /// Reads the locations and the blocks (IPv4 or IPv6) of the GeoLite2 Country CSV database.
+    ///
+    /// The networks are mapped to the country of `geoname_id`, or `registered_country_geoname_id` if empty,
+    /// those without a country (eg. anonymous proxies) are skipped.
+    ///
+    /// ```
+    /// use ip2c::rir::IpCodeMap;
+    ///
+    /// let locations = "\
+    /// geoname_id,locale_code,continent_code,continent_name,country_iso_code,country_name,is_in_european_union
+    /// 1814991,en,AS,Asia,CN,China,0
+    /// 6255148,en,EU,Europe,,,0
+    /// ";
+    /// let blocks = "\
+    /// network,geoname_id,registered_country_geoname_id,represented_country_geoname_id,is_anonymous_proxy,is_satellite_provider
+    /// 1.0.1.0/24,1814991,1814991,,0,0
+    /// 1.0.2.0/23,,1814991,,0,0
+    /// 2.56.8.0/22,6255148,6255148,,0,0
+    /// 2.57.0.0/24,,,,1,0
+    /// ";
+    /// let mut map = IpCodeMap::new();
+    /// assert_eq!(map.read_geolite2_country(locations.as_bytes(), blocks.as_bytes()).unwrap(), 2);
+    /// assert_eq!(map.query("1.0.3.1".parse().unwrap()).unwrap().name(), "CN");
+    /// assert_eq!(map.query("2.56.8.1".parse().unwrap()), None);
+    /// ```
+    pub fn read_geolite2_country(&mut self, locations: impl BufRead, blocks: impl BufRead) -> Result<usize, ImportError> {
+        let locations = read_locations(locations)?;
+        read_blocks(self, &locations, blocks)
+    }
+}
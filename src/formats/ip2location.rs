@@ -0,0 +1,104 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+use crate::formats::{for_each_line, insert_range, parse_code, split_csv, Format, ImportError, ImportErrorKind};
+use crate::rir::IpCodeMap;
+
+const FORMAT: Format = Format::Ip2LocationDb1;
+
+// the IPv4 database numbers the addresses in 32 bits, the IPv6 one in 128 bits
+fn parse_number(s: &str, ipv6: bool) -> Result<IpAddr, ImportErrorKind> {
+    let n: u128 = s.parse().map_err(|_| ImportErrorKind::BadAddress(s.to_string()))?;
+    if ipv6 {
+        return Ok(Ipv6Addr::from(n).into());
+    }
+    u32::try_from(n).map(|n| Ipv4Addr::from(n).into()).map_err(|_| ImportErrorKind::BadAddress(s.to_string()))
+}
+
+impl IpCodeMap {
+    /// Loads the IP2Location LITE DB1 CSV file, eg. `IP2LOCATION-LITE-DB1.CSV`, or `IP2LOCATION-LITE-DB1.IPV6.CSV`
+    /// if `ipv6`, see [IpCodeMap::read_ip2location_db1].
+    ///
+    /// Returns the number of the ranges inserted.
+    pub fn load_ip2location_db1(&mut self, file_path: impl AsRef<Path>, ipv6: bool) -> Result<usize, ImportError> {
+        let path = file_path.as_ref();
+        let file = File::open(path).map_err(|e| ImportError::new(FORMAT, 0, ImportErrorKind::Io(e)).with_path(path))?;
+        self.read_ip2location_db1(BufReader::new(file), ipv6).map_err(|e| e.with_path(path))
+    }
+
+    /// Reads the `"ip_from","ip_to","country_code","country_name"` lines of IP2Location LITE DB1,
+    /// `ipv6` if it is the IPv6 database.
+    ///
+    /// The IPv4 database numbers the addresses in 32 bits, and the IPv6 one in 128 bits, where the IPv4-mapped ranges,
+    /// the numbers from `281470681743360` (`::ffff:0.0.0.0`) to `281474976710655` (`::ffff:255.255.255.255`),
+    /// are inserted as IPv4. The ranges of `-` are unassigned and skipped.
+    ///
+    /// ```
+    /// use ip2c::rir::IpCodeMap;
+    ///
+    /// let csv = r#""0","16777215","-","-"
+    /// "16777216","16777471","AU","Australia"
+    /// "16777472","16778239","CN","China"
+    /// "#;
+    /// let mut map = IpCodeMap::new();
+    /// assert_eq!(map.read_ip2location_db1(csv.as_bytes(), false).unwrap(), 2);
+    /// assert_eq!(map.query("1.0.0.1".parse().unwrap()).unwrap().name(), "AU");
+    /// assert_eq!(map.query("0.0.0.1".parse().unwrap()), None);
+    ///
+    /// let csv = r#""0","281470681743359","-","-"
+    /// "281470698520576","281470698520831","AU","Australia"
+    /// "42540528726795050063891204319802818560","42540528806023212578155541913346768895","JP","Japan"
+    /// "#;
+    /// let mut map = IpCodeMap::new();
+    /// assert_eq!(map.read_ip2location_db1(csv.as_bytes(), true).unwrap(), 2);
+    /// assert_eq!(map.query("1.0.0.1".parse().unwrap()).unwrap().name(), "AU");
+    /// assert_eq!(map.query("2001:200::1".parse().unwrap()).unwrap().name(), "JP");
+    /// ```
+    pub fn read_ip2location_db1(&mut self, r: impl BufRead, ipv6: bool) -> Result<usize, ImportError> {
+        let mut n = 0;
+        for_each_line(FORMAT, r, |_, line| {
+            let fields = split_csv(line);
+            let [from, to, code, _name] = &fields[..] else {
+                return Err(ImportErrorKind::BadColumns(format!("{} columns", fields.len())));
+            };
+            if code == "-" {
+                return Ok(());
+            }
+            insert_range(self, parse_number(from, ipv6)?, parse_number(to, ipv6)?, parse_code(code)?)?;
+            n += 1;
+            Ok(())
+        })?;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::rir::IpCodeMap;
+
+    #[test]
+    fn load_file() {
+        let dir = std::env::temp_dir().join(format!("ip2c-ip2location-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let csv = "\"16777216\",\"16777471\",\"AU\",\"Australia\"\n";
+        fs::write(dir.join("IP2LOCATION-LITE-DB1.CSV"), csv).unwrap();
+        fs::write(dir.join("db1.csv"), "\"1\",\"255\",\"AU\",\"Australia\"\n").unwrap();
+
+        let mut map = IpCodeMap::new();
+        assert_eq!(map.load_ip2location_db1(dir.join("IP2LOCATION-LITE-DB1.CSV"), false).unwrap(), 1);
+        assert_eq!(map.query("1.0.0.1".parse().unwrap()).unwrap().name(), "AU");
+        // the same numbers of the IPv6 database, whatever the name is
+        assert_eq!(map.load_ip2location_db1(dir.join("IP2LOCATION-LITE-DB1.CSV"), true).unwrap(), 1);
+        assert_eq!(map.query("::100:1".parse().unwrap()).unwrap().name(), "AU");
+        assert_eq!(map.load_ip2location_db1(dir.join("db1.csv"), true).unwrap(), 1);
+        assert_eq!(map.query("::1".parse().unwrap()).unwrap().name(), "AU");
+        assert!(map.load_ip2location_db1(dir.join("missing.csv"), false).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,246 @@
+//! Import the country data of other vendors into [IpCodeMap](crate::rir::IpCodeMap), besides the delegated files.
+//!
+//! - GeoLite2 Country CSV, see [IpCodeMap::load_geolite2_country](crate::rir::IpCodeMap::load_geolite2_country)
+//! - DB-IP IP to Country Lite CSV, see [IpCodeMap::load_dbip_country](crate::rir::IpCodeMap::load_dbip_country)
+//! - IP2Location LITE DB1 CSV, see [IpCodeMap::load_ip2location_db1](crate::rir::IpCodeMap::load_ip2location_db1)
+//!
+//! And the geofeeds (RFC 8805) published by the networks, see [GeofeedMap], which may refine the countries
+//! of the delegated files by [IpCodeMap::overlay_geofeed](crate::rir::IpCodeMap::overlay_geofeed).
+
+mod geolite2;
+mod dbip;
+mod ip2location;
+mod geofeed;
+
+pub use geofeed::*;
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::BufRead;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use crate::{Interval, IpTree, IPv4, IPv6};
+use crate::rir::{CountryRegionCode, IpCodeMap};
+
+/// The imported formats
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    GeoLite2Country,
+    DbIpCountryLite,
+    Ip2LocationDb1,
+    Geofeed,
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Format::GeoLite2Country => "geolite2-country",
+            Format::DbIpCountryLite => "dbip-country-lite",
+            Format::Ip2LocationDb1 => "ip2location-db1",
+            Format::Geofeed => "geofeed",
+        })
+    }
+}
+
+/// The error of the importers, where and why.
+///
+/// ```
+/// use ip2c::formats::{Format, ImportErrorKind};
+/// use ip2c::rir::IpCodeMap;
+///
+/// let mut map = IpCodeMap::new();
+/// let e = map.read_dbip_country("1.0.0.0,1.0.0.255,CN\n1.0.1.0,1.0.0.x,CN\n".as_bytes()).unwrap_err();
+/// assert_eq!(e.format, Format::DbIpCountryLite);
+/// assert_eq!(e.line, 2);
+/// assert!(matches!(e.kind, ImportErrorKind::BadAddress(_)));
+/// assert_eq!(e.to_string(), r#"import dbip-country-lite error at line 2: bad address "1.0.0.x""#);
+/// ```
+#[derive(Debug)]
+pub struct ImportError {
+    pub format: Format,
+    /// the file if loaded from a file
+    pub path: Option<PathBuf>,
+    /// 1-based, `0` if it is not the error of a line
+    pub line: usize,
+    pub kind: ImportErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ImportErrorKind {
+    Io(io::Error),
+    /// the header or the number of the columns is unexpected
+    BadColumns(String),
+    BadAddress(String),
+    /// eg. the start is after the end
+    BadRange(String),
+    BadCode(String),
+    /// not an ISO 3166-2 code of the country
+    BadRegion(String),
+    /// the geoname id of GeoLite2 is not in the locations
+    UnknownGeonameId(String),
+    /// the range overlaps the ranges imported
+    Overlap(String),
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("import {} error", self.format))?;
+        if let Some(path) = &self.path {
+            f.write_fmt(format_args!(" in {}", path.display()))?;
+        }
+        if self.line > 0 {
+            f.write_fmt(format_args!(" at line {}", self.line))?;
+        }
+        match &self.kind {
+            ImportErrorKind::Io(e) => f.write_fmt(format_args!(": {}", e)),
+            ImportErrorKind::BadColumns(s) => f.write_fmt(format_args!(": bad columns {:?}", s)),
+            ImportErrorKind::BadAddress(s) => f.write_fmt(format_args!(": bad address {:?}", s)),
+            ImportErrorKind::BadRange(s) => f.write_fmt(format_args!(": bad range {:?}", s)),
+            ImportErrorKind::BadCode(s) => f.write_fmt(format_args!(": bad country code {:?}", s)),
+            ImportErrorKind::BadRegion(s) => f.write_fmt(format_args!(": bad region code {:?}", s)),
+            ImportErrorKind::UnknownGeonameId(s) => f.write_fmt(format_args!(": unknown geoname id {:?}", s)),
+            ImportErrorKind::Overlap(s) => f.write_fmt(format_args!(": {}", s)),
+        }
+    }
+}
+
+impl Error for ImportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            ImportErrorKind::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl ImportError {
+    fn new(format: Format, line: usize, kind: ImportErrorKind) -> Self {
+        ImportError { format, path: None, line, kind }
+    }
+
+    fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+}
+
+// the non-empty lines and their 1-based numbers, the io error is the error of the format
+fn for_each_line(format: Format, r: impl BufRead, mut f: impl FnMut(usize, &str) -> Result<(), ImportErrorKind>) -> Result<(), ImportError> {
+    for (i, line) in r.lines().enumerate() {
+        let line = line.map_err(|e| ImportError::new(format, i + 1, ImportErrorKind::Io(e)))?;
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            continue;
+        }
+        f(i + 1, line).map_err(|kind| ImportError::new(format, i + 1, kind))?;
+    }
+    Ok(())
+}
+
+fn parse_code(s: &str) -> Result<CountryRegionCode, ImportErrorKind> {
+    CountryRegionCode::new(s).filter(|c| c.name().bytes().all(|b| b.is_ascii_uppercase()))
+        .ok_or_else(|| ImportErrorKind::BadCode(s.to_string()))
+}
+
+// parses the network, eg. `1.0.0.0/24` or `2001:200::/32`, and inserts it
+fn insert_network<V>(map: &mut IpTree<V>, network: &str, value: V) -> Result<(), ImportErrorKind> {
+    let bad = || ImportErrorKind::BadAddress(network.to_string());
+    if network.contains(':') {
+        let span: Interval<IPv6> = network.parse().map_err(|_| bad())?;
+        map.ipv6.insert(span, value).map_err(|e| ImportErrorKind::Overlap(e.to_string()))
+    } else {
+        let span: Interval<IPv4> = network.parse().map_err(|_| bad())?;
+        map.ipv4.insert(span, value).map_err(|e| ImportErrorKind::Overlap(e.to_string()))
+    }
+}
+
+// inserts the range of the same family, the IPv4-mapped IPv6 range is inserted as IPv4
+fn insert_range(map: &mut IpCodeMap, start: IpAddr, end: IpAddr, code: CountryRegionCode) -> Result<(), ImportErrorKind> {
+    let bad = || ImportErrorKind::BadRange(format!("{}-{}", start, end));
+    let r = match (start, end) {
+        (IpAddr::V4(s), IpAddr::V4(e)) if s <= e => map.ipv4.insert(Interval(s.into(), e.into()), code),
+        (IpAddr::V6(s), IpAddr::V6(e)) if s <= e => match (s.to_ipv4_mapped(), e.to_ipv4_mapped()) {
+            (Some(s), Some(e)) => map.ipv4.insert(Interval(s.into(), e.into()), code),
+            (None, None) => return map.ipv6.insert(Interval(s.into(), e.into()), code)
+                .map_err(|e| ImportErrorKind::Overlap(e.to_string())),
+            _ => return Err(bad()),
+        },
+        _ => return Err(bad()),
+    };
+    r.map_err(|e| ImportErrorKind::Overlap(e.to_string()))
+}
+
+// the fields of a csv line, the quoted fields may contain commas and `""`
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formats::{split_csv, ImportError};
+    use crate::rir::IpCodeMap;
+
+    const LOCATIONS: &str = "geoname_id,locale_code,continent_code,continent_name,country_iso_code,country_name\n\
+                             1861060,en,AS,Asia,JP,Japan\n";
+
+    #[test]
+    fn csv() {
+        assert_eq!(split_csv(r#""16777216","16777471","AU","Australia""#), ["16777216", "16777471", "AU", "Australia"]);
+        assert_eq!(split_csv(r#"1,"Korea, Republic of","say ""hi""",,"#), ["1", "Korea, Republic of", "say \"hi\"", "", ""]);
+    }
+
+    #[test]
+    fn errors() {
+        let geolite2 = |blocks: &str| IpCodeMap::new().read_geolite2_country(LOCATIONS.as_bytes(), blocks.as_bytes());
+        let dbip = |csv: &str| IpCodeMap::new().read_dbip_country(csv.as_bytes());
+        let ip2location = |csv: &str, ipv6| IpCodeMap::new().read_ip2location_db1(csv.as_bytes(), ipv6);
+        // the error and the start of its message
+        let cases: [(Result<usize, ImportError>, &str); 16] = [
+            (ip2location("\"16777216\",\"16777471\",\"AU\"\n", false), "import ip2location-db1 error at line 1: bad columns \"3 columns\""),
+            (ip2location("\"1x\",\"16777471\",\"AU\",\"Australia\"\n", false), "import ip2location-db1 error at line 1: bad address \"1x\""),
+            (ip2location("\"16777216\",\"4294967296\",\"AU\",\"Australia\"\n", false), "import ip2location-db1 error at line 1: bad address \"4294967296\""),
+            (ip2location("\"16777471\",\"16777216\",\"AU\",\"Australia\"\n", false), "import ip2location-db1 error at line 1: bad range \"1.0.0.255-1.0.0.0\""),
+            // a range across the end of the IPv4-mapped addresses
+            (ip2location("\"281474976710655\",\"281474976710656\",\"AU\",\"Australia\"\n", true), "import ip2location-db1 error at line 1: bad range \"::ffff:255.255.255.255-::1:0:0:0\""),
+            (ip2location("\"16777216\",\"16777471\",\"AU\",\"Australia\"\n\"16777400\",\"16777500\",\"CN\",\"China\"\n", false), "import ip2location-db1 error at line 2: interval conflict"),
+            (dbip("1.0.0.0,1.0.0.255\n"), "import dbip-country-lite error at line 1: bad columns \"2 columns\""),
+            (dbip("1.0.0.255,1.0.0.0,AU\n"), "import dbip-country-lite error at line 1: bad range \"1.0.0.255-1.0.0.0\""),
+            (dbip("\n1.0.0.0,::ff,AU\n"), "import dbip-country-lite error at line 2: bad range \"1.0.0.0-::ff\""),
+            (dbip("1.0.0.0,1.0.0.255,Australia\n"), "import dbip-country-lite error at line 1: bad country code \"Australia\""),
+            (IpCodeMap::new().load_dbip_country("./no-such-file.csv"), "import dbip-country-lite error in ./no-such-file.csv: "),
+            (geolite2("network,geoname_id,registered_country_geoname_id\n2001:200::/32,1861060,\n2001:200::/48,1861060,\n"), "import geolite2-country error at line 3: interval conflict"),
+            (geolite2("network,geoname_id,registered_country_geoname_id\n1.0.0.0/8,42,\n"), "import geolite2-country error at line 2: unknown geoname id \"42\""),
+            (geolite2("network,geoname_id\n"), "import geolite2-country error at line 1: bad columns \"no registered_country_geoname_id column\""),
+            (IpCodeMap::new().read_geolite2_country("geoname_id,country_iso_code\n1,usa\n".as_bytes(), "".as_bytes()), "import geolite2-country error at line 2: bad country code \"usa\""),
+            (IpCodeMap::new().load_geolite2_country("./no-such-dir"), "import geolite2-country error in ./no-such-dir/GeoLite2-Country-Locations-en.csv: "),
+        ];
+        for (r, expected) in cases {
+            let e = r.unwrap_err().to_string();
+            assert!(e.starts_with(expected), "{} does not start with {}", e, expected);
+        }
+
+        // the ranges before the error are kept
+        let mut map = IpCodeMap::new();
+        let blocks = "network,geoname_id,registered_country_geoname_id\n2001:200::/32,1861060,\n2001:200::/48,1861060,\n";
+        assert!(map.read_geolite2_country(LOCATIONS.as_bytes(), blocks.as_bytes()).is_err());
+        assert_eq!(map.query("2001:200:1::".parse().unwrap()).unwrap().name(), "JP");
+    }
+}
@@ -1,18 +1,113 @@
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 use std::net::{Ipv4Addr, Ipv6Addr, AddrParseError};
-use crate::itree::{IntervalTreeMap};
+use crate::itree::{Discrete, Distance, Interval, IntervalTreeMap};
 
 /// similar with [Ipv4Addr]
 /// use `.into()` and `.from()` to convert between them
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct IPv4(pub(crate) u32);
 
 /// similar with [Ipv6Addr]
 /// use `.into()` and `.from()` to convert between them
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct IPv6(pub(crate) u128);
 
+/// The common bits view of [IPv4] and [IPv6]
+pub trait IpAddress: Copy + Ord {
+    /// the length of the address in bits
+    const BITS: u8;
+
+    fn to_bits(self) -> u128;
+
+    /// the high bits are truncated
+    fn from_bits(bits: u128) -> Self;
+}
+
+impl IpAddress for IPv4 {
+    const BITS: u8 = 32;
+
+    fn to_bits(self) -> u128 {
+        self.0 as u128
+    }
+
+    fn from_bits(bits: u128) -> Self {
+        IPv4(bits as u32)
+    }
+}
+
+impl IpAddress for IPv6 {
+    const BITS: u8 = 128;
+
+    fn to_bits(self) -> u128 {
+        self.0
+    }
+
+    fn from_bits(bits: u128) -> Self {
+        IPv6(bits)
+    }
+}
+
+impl Discrete for IPv4 {
+    fn next(self) -> Option<Self> {
+        self.0.checked_add(1).map(IPv4)
+    }
+
+    fn prev(self) -> Option<Self> {
+        self.0.checked_sub(1).map(IPv4)
+    }
+}
+
+impl Discrete for IPv6 {
+    fn next(self) -> Option<Self> {
+        self.0.checked_add(1).map(IPv6)
+    }
+
+    fn prev(self) -> Option<Self> {
+        self.0.checked_sub(1).map(IPv6)
+    }
+}
+
+impl Distance for IPv4 {
+    fn distance(self, other: Self) -> u128 {
+        self.0.abs_diff(other.0) as u128
+    }
+}
+
+impl Distance for IPv6 {
+    fn distance(self, other: Self) -> u128 {
+        self.0.abs_diff(other.0)
+    }
+}
+
+impl Interval<IPv4> {
+    /// the number of addresses
+    ///
+    /// ```
+    /// use ip2c::{Interval, IPv4};
+    ///
+    /// let v: Interval<IPv4> = "10.0.0.0/8".parse().unwrap();
+    /// assert_eq!(v.size(), 1 << 24);
+    /// ```
+    pub fn size(&self) -> u64 {
+        (self.1.0 - self.0.0) as u64 + 1
+    }
+}
+
+impl Interval<IPv6> {
+    /// the number of addresses, saturated to `u128::MAX` for the whole `::/0`
+    ///
+    /// ```
+    /// use ip2c::{Interval, IPv6};
+    ///
+    /// let v: Interval<IPv6> = "2001:db8::/64".parse().unwrap();
+    /// assert_eq!(v.size(), 1 << 64);
+    /// ```
+    pub fn size(&self) -> u128 {
+        (self.1.0 - self.0.0).saturating_add(1)
+    }
+}
+
 pub type Ipv4Tree<T> = IntervalTreeMap<IPv4, T>;
 pub type Ipv6Tree<T> = IntervalTreeMap<IPv6, T>;
 
@@ -31,6 +126,12 @@ impl<T> IpTree<T> {
     }
 }
 
+impl<T> Default for IpTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Display for IPv4 {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         Display::fmt(&Ipv4Addr::from(self.0), f)
@@ -49,9 +150,9 @@ impl From<u32> for IPv4 {
     }
 }
 
-impl Into<u32> for IPv4 {
-    fn into(self) -> u32 {
-        self.0
+impl From<IPv4> for u32 {
+    fn from(ip: IPv4) -> Self {
+        ip.0
     }
 }
 
@@ -69,9 +170,9 @@ impl FromStr for IPv4 {
     }
 }
 
-impl Into<Ipv4Addr> for IPv4 {
-    fn into(self) -> Ipv4Addr {
-        Ipv4Addr::from(self.0)
+impl From<IPv4> for Ipv4Addr {
+    fn from(ip: IPv4) -> Self {
+        Ipv4Addr::from(ip.0)
     }
 }
 
@@ -81,9 +182,9 @@ impl From<u128> for IPv6 {
     }
 }
 
-impl Into<u128> for IPv6 {
-    fn into(self) -> u128 {
-        self.0
+impl From<IPv6> for u128 {
+    fn from(ip: IPv6) -> Self {
+        ip.0
     }
 }
 
@@ -101,8 +202,8 @@ impl FromStr for IPv6 {
     }
 }
 
-impl Into<Ipv6Addr> for IPv6 {
-    fn into(self) -> Ipv6Addr {
-        Ipv6Addr::from(self.0)
+impl From<IPv6> for Ipv6Addr {
+    fn from(ip: IPv6) -> Self {
+        Ipv6Addr::from(ip.0)
     }
 }
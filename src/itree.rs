@@ -1,8 +1,11 @@
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
+use std::collections::btree_map;
 use std::collections::btree_map::Entry::{Vacant, Occupied};
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
+use std::iter::FusedIterator;
+use std::ops::Bound;
 
 /// [Interval] is a closed range.
 /// `Scope(0,4)` contains `[0,4]`, can be `from((0,4))`
@@ -32,6 +35,152 @@ impl<T: Ord + Copy> Display for Interval<T> where T: Display {
     }
 }
 
+/// Discrete keys, which have the next and the previous value.
+/// Needed by the interval algebra, eg. `[0, 4]` and `[5, 9]` are adjacent.
+pub trait Discrete: Ord + Copy {
+    /// `None` if it is the max value
+    fn next(self) -> Option<Self>;
+    /// `None` if it is the min value
+    fn prev(self) -> Option<Self>;
+}
+
+macro_rules! impl_discrete {
+    ($($t:ty),*) => {
+        $(impl Discrete for $t {
+            fn next(self) -> Option<Self> {
+                self.checked_add(1)
+            }
+
+            fn prev(self) -> Option<Self> {
+                self.checked_sub(1)
+            }
+        })*
+    };
+}
+
+/// The number of steps between two keys, eg. `3.distance(5) == 2`
+pub trait Distance: Discrete {
+    fn distance(self, other: Self) -> u128;
+}
+
+macro_rules! impl_distance {
+    ($($t:ty),*) => {
+        $(impl Distance for $t {
+            fn distance(self, other: Self) -> u128 {
+                self.abs_diff(other) as u128
+            }
+        })*
+    };
+}
+
+impl_discrete!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+impl_distance!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl<T: Ord + Copy> Interval<T> {
+    /// ```
+    /// use ip2c::Interval;
+    ///
+    /// assert!(Interval(0, 4).contains(4));
+    /// assert!(!Interval(0, 4).contains(5));
+    /// ```
+    pub fn contains(&self, point: T) -> bool {
+        self.0 <= point && point <= self.1
+    }
+
+    /// Returns `true` if the other interval is a subset of this one.
+    ///
+    /// ```
+    /// use ip2c::Interval;
+    ///
+    /// assert!(Interval(0, 4).contains_interval(&Interval(1, 4)));
+    /// assert!(!Interval(0, 4).contains_interval(&Interval(3, 5)));
+    /// ```
+    pub fn contains_interval(&self, other: &Interval<T>) -> bool {
+        self.0 <= other.0 && other.1 <= self.1
+    }
+
+    /// ```
+    /// use ip2c::Interval;
+    ///
+    /// assert_eq!(Interval(0, 4).intersection(&Interval(3, 9)), Some(Interval(3, 4)));
+    /// assert_eq!(Interval(0, 4).intersection(&Interval(5, 9)), None);
+    /// ```
+    pub fn intersection(&self, other: &Interval<T>) -> Option<Interval<T>> {
+        let start = self.0.max(other.0);
+        let end = self.1.min(other.1);
+        if start <= end { Some(Interval(start, end)) } else { None }
+    }
+}
+
+impl<T: Discrete> Interval<T> {
+    /// Returns `true` if the two intervals do not overlap, and no point is between them.
+    ///
+    /// ```
+    /// use ip2c::Interval;
+    ///
+    /// assert!(Interval(0, 4).is_adjacent(&Interval(5, 9)));
+    /// assert!(!Interval(0, 4).is_adjacent(&Interval(4, 9)));
+    /// assert!(!Interval(0, 4).is_adjacent(&Interval(6, 9)));
+    /// ```
+    pub fn is_adjacent(&self, other: &Interval<T>) -> bool {
+        self.1.next() == Some(other.0) || other.1.next() == Some(self.0)
+    }
+
+    /// Returns the union if the two intervals overlap or are adjacent.
+    ///
+    /// ```
+    /// use ip2c::Interval;
+    ///
+    /// assert_eq!(Interval(0, 4).union_if_touching(&Interval(5, 9)), Some(Interval(0, 9)));
+    /// assert_eq!(Interval(0, 4).union_if_touching(&Interval(2, 3)), Some(Interval(0, 4)));
+    /// assert_eq!(Interval(0, 4).union_if_touching(&Interval(6, 9)), None);
+    /// ```
+    pub fn union_if_touching(&self, other: &Interval<T>) -> Option<Interval<T>> {
+        if self.intersection(other).is_none() && !self.is_adjacent(other) {
+            return None;
+        }
+        Some(Interval(self.0.min(other.0), self.1.max(other.1)))
+    }
+
+    /// Returns the parts of this interval not in the other, the one before it and the one after it.
+    ///
+    /// ```
+    /// use ip2c::Interval;
+    ///
+    /// assert_eq!(Interval(0, 9).difference(&Interval(3, 4)), (Some(Interval(0, 2)), Some(Interval(5, 9))));
+    /// assert_eq!(Interval(0, 9).difference(&Interval(0, 4)), (None, Some(Interval(5, 9))));
+    /// assert_eq!(Interval(0, 4).difference(&Interval(6, 9)), (Some(Interval(0, 4)), None));
+    /// assert_eq!(Interval(3, 4).difference(&Interval(0, 9)), (None, None));
+    /// ```
+    pub fn difference(&self, other: &Interval<T>) -> (Option<Interval<T>>, Option<Interval<T>>) {
+        if self.intersection(other).is_none() {
+            return if self.1 < other.0 { (Some(*self), None) } else { (None, Some(*self)) };
+        }
+        let before = if self.0 < other.0 { other.0.prev().map(|e| Interval(self.0, e)) } else { None };
+        let after = if other.1 < self.1 { other.1.next().map(|s| Interval(s, self.1)) } else { None };
+        (before, after)
+    }
+
+    /// Split into the part before the point and the part starts from the point.
+    ///
+    /// ```
+    /// use ip2c::Interval;
+    ///
+    /// assert_eq!(Interval(0, 9).split_at(5), (Some(Interval(0, 4)), Some(Interval(5, 9))));
+    /// assert_eq!(Interval(0, 9).split_at(0), (None, Some(Interval(0, 9))));
+    /// assert_eq!(Interval(0, 9).split_at(10), (Some(Interval(0, 9)), None));
+    /// ```
+    pub fn split_at(&self, point: T) -> (Option<Interval<T>>, Option<Interval<T>>) {
+        if point <= self.0 {
+            (None, Some(*self))
+        } else if point > self.1 {
+            (Some(*self), None)
+        } else {
+            (point.prev().map(|e| Interval(self.0, e)), Some(Interval(point, self.1)))
+        }
+    }
+}
+
 impl<T> PartialOrd for Interval<T> where T: Ord + Copy {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -41,8 +190,8 @@ impl<T> PartialOrd for Interval<T> where T: Ord + Copy {
 /// `==` Eq is Transitive Equal. But `Ord::cmp` return `Ordering::Equal` only means that intersection is non empty set
 impl<T> Ord for Interval<T> where T: Ord + Copy {
     fn cmp(&self, that: &Self) -> Ordering {
-        debug_assert_eq!(self.0 <= self.1, true);
-        debug_assert_eq!(that.0 <= that.1, true);
+        debug_assert!(self.0 <= self.1);
+        debug_assert!(that.0 <= that.1);
         if self.1 < that.0 { Ordering::Less } else if self.0 > that.1 { Ordering::Greater } else { Ordering::Equal }
     }
 }
@@ -100,6 +249,11 @@ impl<K: Ord + Copy, V> IntervalTreeMap<K, V> {
         self.map.len()
     }
 
+    /// Returns `true` if the map contains no interval.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
     /// Returns a reference to the value corresponding to the point.
     ///
     /// # Examples
@@ -137,6 +291,107 @@ impl<K: Ord + Copy, V> IntervalTreeMap<K, V> {
         self.map.get_key_value(&key)
     }
 
+    /// Returns a mutable reference to the value corresponding to the point.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    /// ```
+    /// use ip2c::IntervalTreeMap;
+    ///
+    /// let mut map = IntervalTreeMap::new();
+    /// let _ = map.insert_scope(0, 4, 1);
+    /// if let Some(v) = map.query_mut(3) {
+    ///     *v += 1;
+    /// }
+    /// assert_eq!(map.query(0), Some(&2));
+    /// ```
+    pub fn query_mut(&mut self, point: K) -> Option<&mut V> {
+        let key = Interval(point, point);
+        self.map.get_mut(&key)
+    }
+
+    /// Returns the key and a mutable reference to the value corresponding to the point.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    /// ```
+    /// use ip2c::{Interval, IntervalTreeMap};
+    ///
+    /// let mut map = IntervalTreeMap::new();
+    /// let _ = map.insert_scope(0, 4, 1);
+    /// let (k, v) = map.get_key_value_mut(3).unwrap();
+    /// *v += 1;
+    /// assert_eq!(k, &Interval(0, 4));
+    /// assert_eq!(map.query(0), Some(&2));
+    /// ```
+    pub fn get_key_value_mut(&mut self, point: K) -> Option<(&Interval<K>, &mut V)> {
+        let key = Interval(point, point);
+        self.map.range_mut(key..=key).next()
+    }
+
+    /// Gets the entry of the interval for in-place manipulation.
+    ///
+    /// The entry is [Entry::Occupied] only if the exactly same interval is in the map,
+    /// and [Entry::Conflicting] if another interval overlaps with it.
+    /// If the interval is invalid(eg. `Interval(5, 3)`), [IntervalError::Invalid] is returned like [IntervalTreeMap::insert].
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    /// ```
+    /// use ip2c::{Entry, Interval, IntervalTreeMap};
+    ///
+    /// let mut map = IntervalTreeMap::new();
+    /// *map.entry(Interval(0, 4)).unwrap().or_insert(0).unwrap() += 1;
+    /// *map.entry(Interval(0, 4)).unwrap().or_insert(0).unwrap() += 1;
+    /// assert_eq!(map.query(2), Some(&2));
+    /// assert!(map.entry(Interval(3, 9)).unwrap().or_insert(0).is_err());
+    /// match map.entry(Interval(3, 9)).unwrap() {
+    ///     Entry::Conflicting(e) => assert_eq!(e.conflict(), &Interval(0, 4)),
+    ///     _ => unreachable!(),
+    /// }
+    /// assert!(map.entry(Interval(9, 3)).is_err());
+    /// ```
+    pub fn entry(&mut self, key: Interval<K>) -> Result<Entry<'_, K, V>, IntervalError<K>> {
+        if key.0 > key.1 {
+            Err(IntervalError::Invalid([key, Interval(key.1, key.0)]))?
+        }
+        Ok(match self.map.entry(key) {
+            Vacant(e) => Entry::Vacant(VacantEntry { inner: e }),
+            Occupied(e) => {
+                if *e.key() == key {
+                    Entry::Occupied(OccupiedEntry { inner: e })
+                } else {
+                    Entry::Conflicting(ConflictingEntry { key, inner: e })
+                }
+            }
+        })
+    }
+
+    /// Retains only the intervals specified by the predicate, the values can be modified in place.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    /// ```
+    /// use ip2c::IntervalTreeMap;
+    ///
+    /// let mut map = IntervalTreeMap::new();
+    /// let _ = map.insert_scope(0, 4, 1);
+    /// let _ = map.insert_scope(5, 9, 2);
+    /// map.retain(|_, v| {
+    ///     *v *= 10;
+    ///     *v > 10
+    /// });
+    /// assert_eq!(map.len(), 1);
+    /// assert_eq!(map.query(5), Some(&20));
+    /// ```
+    pub fn retain<F>(&mut self, f: F) where F: FnMut(&Interval<K>, &mut V) -> bool {
+        self.map.retain(f)
+    }
+
     /// Inserts an interval-value pair into the map.
     ///
     /// If the interval is invalid(eg. `Interval(5, 3)`), [IntervalError::Invalid]` is returned
@@ -181,6 +436,7 @@ impl<K: Ord + Copy, V> IntervalTreeMap<K, V> {
     }
 
     /// Removes an interval from the map, returning the value of the interval if the exactly same interval was previously in the map.
+    /// Use [IntervalTreeMap::remove_range] to clear a span which may partially overlap the intervals.
     ///
     /// # Examples
     ///
@@ -206,13 +462,614 @@ impl<K: Ord + Copy, V> IntervalTreeMap<K, V> {
     }
 
     fn _remove(&mut self, key: &Interval<K>) -> Option<V> {
-        self.map.remove(&key)
+        self.map.remove(key)
     }
 
-    /// Return the reference of the inner map, so you can iterate on it.
+    /// Return the reference of the inner map.
+    /// Prefer [IntervalTreeMap::iter] and [IntervalTreeMap::range] to iterate on it, the inner map may change.
     pub fn tree(&self) -> &BTreeMap<Interval<K>, V> {
         &self.map
     }
+
+    /// Gets an iterator over the interval-value pairs, sorted by interval.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    /// ```
+    /// use ip2c::{Interval, IntervalTreeMap};
+    ///
+    /// let mut map = IntervalTreeMap::new();
+    /// let _ = map.insert_scope(5, 9, "b");
+    /// let _ = map.insert_scope(0, 4, "a");
+    /// let v: Vec<_> = map.iter().collect();
+    /// assert_eq!(v, [(&Interval(0, 4), &"a"), (&Interval(5, 9), &"b")]);
+    /// assert_eq!(map.iter().next_back(), Some((&Interval(5, 9), &"b")));
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter { inner: self.map.iter() }
+    }
+
+    /// Gets a mutable iterator over the interval-value pairs, sorted by interval.
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut { inner: self.map.iter_mut() }
+    }
+
+    /// Gets an iterator over the intervals, sorted.
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.map.keys() }
+    }
+
+    /// Gets an iterator over the values, sorted by interval.
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values { inner: self.map.values() }
+    }
+
+    /// Gets a mutable iterator over the values, sorted by interval.
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut { inner: self.map.values_mut() }
+    }
+
+    /// Gets an iterator over the interval-value pairs which overlap with the span, sorted by interval.
+    /// The iterator is empty if the span is invalid(eg. `Interval(5, 3)`).
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    /// ```
+    /// use ip2c::{Interval, IntervalTreeMap};
+    ///
+    /// let mut map = IntervalTreeMap::new();
+    /// let _ = map.insert_scope(0, 4, "a");
+    /// let _ = map.insert_scope(5, 9, "b");
+    /// let _ = map.insert_scope(10, 14, "c");
+    /// let v: Vec<_> = map.range(Interval(3, 7)).map(|(_, v)| *v).collect();
+    /// assert_eq!(v, ["a", "b"]);
+    /// assert_eq!(map.range(Interval(3, 12)).next_back(), Some((&Interval(10, 14), &"c")));
+    /// assert_eq!(map.range(Interval(15, 20)).next(), None);
+    /// assert_eq!(map.range(Interval(7, 3)).next(), None);
+    /// ```
+    pub fn range(&self, span: Interval<K>) -> Range<'_, K, V> {
+        if span.0 > span.1 {
+            let empty = Interval(span.0, span.0);
+            return Range { inner: self.map.range(empty..empty) };
+        }
+        Range { inner: self.map.range(Interval(span.0, span.0)..=Interval(span.1, span.1)) }
+    }
+}
+
+impl<K: Discrete, V: Clone> IntervalTreeMap<K, V> {
+    /// Clears the span from the map, the partially overlapped intervals are trimmed or split.
+    /// Returns the removed pieces, with the cloned values, nothing is removed if the span is invalid(eg. `Interval(5, 3)`).
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    /// ```
+    /// use ip2c::{Interval, IntervalTreeMap};
+    ///
+    /// let mut map = IntervalTreeMap::new();
+    /// let _ = map.insert(Interval(0, 4), "a");
+    /// let _ = map.insert(Interval(6, 7), "b");
+    /// assert_eq!(map.remove_range(Interval(2, 6)), vec![(Interval(2, 4), "a"), (Interval(6, 6), "b")]);
+    /// assert_eq!(map.get_key_value(1), Some((&Interval(0, 1), &"a")));
+    /// assert_eq!(map.get_key_value(7), Some((&Interval(7, 7), &"b")));
+    /// assert_eq!(map.query(3), None);
+    /// ```
+    pub fn remove_range(&mut self, span: Interval<K>) -> Vec<(Interval<K>, V)> {
+        if span.0 > span.1 {
+            return Vec::new();
+        }
+        let keys: Vec<Interval<K>> = self.range(span).map(|(k, _)| *k).collect();
+        let mut removed = Vec::with_capacity(keys.len());
+        for k in keys {
+            let Some(v) = self.map.remove(&k) else { continue };
+            let (before, after) = k.difference(&span);
+            if let Some(b) = before {
+                self.map.insert(b, v.clone());
+            }
+            if let Some(a) = after {
+                self.map.insert(a, v.clone());
+            }
+            if let Some(i) = k.intersection(&span) {
+                removed.push((i, v));
+            }
+        }
+        removed
+    }
+}
+
+/// A stored interval near a point, see [IntervalTreeMap::nearest]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Neighbor<'a, K: Ord + Copy, V> {
+    pub key: &'a Interval<K>,
+    pub value: &'a V,
+    /// the steps from the point to the interval, `0` if the interval contains the point
+    pub distance: u128,
+}
+
+impl<K: Distance, V> IntervalTreeMap<K, V> {
+    /// The nearest interval entirely below the point.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    /// ```
+    /// use ip2c::{Interval, IntervalTreeMap};
+    ///
+    /// let mut map = IntervalTreeMap::new();
+    /// let _ = map.insert_scope(0, 4, "a");
+    /// let _ = map.insert_scope(10, 14, "b");
+    /// let n = map.predecessor(7).unwrap();
+    /// assert_eq!((n.key, n.value, n.distance), (&Interval(0, 4), &"a", 3));
+    /// assert!(map.predecessor(12).is_some());
+    /// assert!(map.predecessor(2).is_none());
+    /// ```
+    pub fn predecessor(&self, point: K) -> Option<Neighbor<'_, K, V>> {
+        let (key, value) = self.map.range(..Interval(point, point)).next_back()?;
+        Some(Neighbor { key, value, distance: point.distance(key.1) })
+    }
+
+    /// The nearest interval entirely above the point.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    /// ```
+    /// use ip2c::{Interval, IntervalTreeMap};
+    ///
+    /// let mut map = IntervalTreeMap::new();
+    /// let _ = map.insert_scope(0, 4, "a");
+    /// let _ = map.insert_scope(10, 14, "b");
+    /// let n = map.successor(7).unwrap();
+    /// assert_eq!((n.key, n.value, n.distance), (&Interval(10, 14), &"b", 3));
+    /// assert!(map.successor(12).is_none());
+    /// ```
+    pub fn successor(&self, point: K) -> Option<Neighbor<'_, K, V>> {
+        let key = Interval(point, point);
+        let (key, value) = self.map.range((Bound::Excluded(key), Bound::Unbounded)).next()?;
+        Some(Neighbor { key, value, distance: key.0.distance(point) })
+    }
+
+    /// The interval contains the point, or the nearer one of [IntervalTreeMap::predecessor] and [IntervalTreeMap::successor].
+    /// The predecessor is preferred if they are at the same distance.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    /// ```
+    /// use ip2c::{Interval, IntervalTreeMap};
+    ///
+    /// let mut map = IntervalTreeMap::new();
+    /// let _ = map.insert_scope(0, 4, "a");
+    /// let _ = map.insert_scope(10, 14, "b");
+    /// assert_eq!(map.nearest(3).unwrap().distance, 0);
+    /// assert_eq!(map.nearest(7).unwrap().value, &"a");
+    /// assert_eq!(map.nearest(8).unwrap().value, &"b");
+    /// ```
+    pub fn nearest(&self, point: K) -> Option<Neighbor<'_, K, V>> {
+        if let Some((key, value)) = self.get_key_value(point) {
+            return Some(Neighbor { key, value, distance: 0 });
+        }
+        match (self.predecessor(point), self.successor(point)) {
+            (Some(p), Some(s)) => Some(if s.distance < p.distance { s } else { p }),
+            (p, s) => p.or(s),
+        }
+    }
+}
+
+/// A view into a single entry of [IntervalTreeMap], see [IntervalTreeMap::entry]
+pub enum Entry<'a, K: Ord + Copy, V> {
+    /// no interval overlaps with the key
+    Vacant(VacantEntry<'a, K, V>),
+    /// the exactly same interval is in the map
+    Occupied(OccupiedEntry<'a, K, V>),
+    /// another interval overlaps with the key
+    Conflicting(ConflictingEntry<'a, K, V>),
+}
+
+pub struct VacantEntry<'a, K: Ord + Copy, V> {
+    inner: btree_map::VacantEntry<'a, Interval<K>, V>,
+}
+
+pub struct OccupiedEntry<'a, K: Ord + Copy, V> {
+    inner: btree_map::OccupiedEntry<'a, Interval<K>, V>,
+}
+
+pub struct ConflictingEntry<'a, K: Ord + Copy, V> {
+    key: Interval<K>,
+    inner: btree_map::OccupiedEntry<'a, Interval<K>, V>,
+}
+
+impl<'a, K: Ord + Copy, V> Entry<'a, K, V> {
+    /// the interval used to get the entry
+    pub fn key(&self) -> &Interval<K> {
+        match self {
+            Entry::Vacant(e) => e.key(),
+            Entry::Occupied(e) => e.key(),
+            Entry::Conflicting(e) => e.key(),
+        }
+    }
+
+    /// Inserts the default if vacant, [IntervalError::Conflict] is returned if conflicting.
+    pub fn or_insert(self, default: V) -> Result<&'a mut V, IntervalError<K>> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Inserts the result of `f` if vacant, [IntervalError::Conflict] is returned if conflicting.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, f: F) -> Result<&'a mut V, IntervalError<K>> {
+        match self {
+            Entry::Vacant(e) => Ok(e.insert(f())),
+            Entry::Occupied(e) => Ok(e.into_mut()),
+            Entry::Conflicting(e) => Err(e.error()),
+        }
+    }
+
+    /// Modifies the value if occupied.
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(e) = &mut self {
+            f(e.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K: Ord + Copy, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &Interval<K> {
+        self.inner.key()
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.inner.insert(value)
+    }
+}
+
+impl<'a, K: Ord + Copy, V> OccupiedEntry<'a, K, V> {
+    pub fn key(&self) -> &Interval<K> {
+        self.inner.key()
+    }
+
+    pub fn get(&self) -> &V {
+        self.inner.get()
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        self.inner.get_mut()
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        self.inner.into_mut()
+    }
+
+    /// Replaces the value, and returns the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        self.inner.insert(value)
+    }
+
+    pub fn remove(self) -> V {
+        self.inner.remove()
+    }
+}
+
+impl<'a, K: Ord + Copy, V> ConflictingEntry<'a, K, V> {
+    /// the interval used to get the entry
+    pub fn key(&self) -> &Interval<K> {
+        &self.key
+    }
+
+    /// one of the intervals in the map overlaps with the key
+    pub fn conflict(&self) -> &Interval<K> {
+        self.inner.key()
+    }
+
+    /// the value of the conflicting interval
+    pub fn get(&self) -> &V {
+        self.inner.get()
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        self.inner.get_mut()
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        self.inner.into_mut()
+    }
+
+    /// Removes the conflicting interval from the map.
+    pub fn remove_entry(self) -> (Interval<K>, V) {
+        self.inner.remove_entry()
+    }
+
+    /// the same error as [IntervalTreeMap::insert] returns
+    pub fn error(&self) -> IntervalError<K> {
+        IntervalError::Conflict([self.key, *self.inner.key()])
+    }
+}
+
+/// An iterator over the entries of [IntervalTreeMap], see [IntervalTreeMap::iter]
+pub struct Iter<'a, K: Ord + Copy, V> {
+    inner: btree_map::Iter<'a, Interval<K>, V>,
+}
+
+/// A mutable iterator over the entries of [IntervalTreeMap], see [IntervalTreeMap::iter_mut]
+pub struct IterMut<'a, K: Ord + Copy, V> {
+    inner: btree_map::IterMut<'a, Interval<K>, V>,
+}
+
+/// An owning iterator over the entries of [IntervalTreeMap]
+pub struct IntoIter<K: Ord + Copy, V> {
+    inner: btree_map::IntoIter<Interval<K>, V>,
+}
+
+/// An iterator over the intervals of [IntervalTreeMap], see [IntervalTreeMap::keys]
+pub struct Keys<'a, K: Ord + Copy, V> {
+    inner: btree_map::Keys<'a, Interval<K>, V>,
+}
+
+/// An iterator over the values of [IntervalTreeMap], see [IntervalTreeMap::values]
+pub struct Values<'a, K: Ord + Copy, V> {
+    inner: btree_map::Values<'a, Interval<K>, V>,
+}
+
+/// A mutable iterator over the values of [IntervalTreeMap], see [IntervalTreeMap::values_mut]
+pub struct ValuesMut<'a, K: Ord + Copy, V> {
+    inner: btree_map::ValuesMut<'a, Interval<K>, V>,
+}
+
+/// An iterator over the overlapped entries of [IntervalTreeMap], see [IntervalTreeMap::range]
+pub struct Range<'a, K: Ord + Copy, V> {
+    inner: btree_map::Range<'a, Interval<K>, V>,
+}
+
+// delegate to the inner iterator
+macro_rules! impl_iterator {
+    ($name:ident<$($lt:lifetime,)? K, V>, $item:ty, $exact:tt) => {
+        impl<$($lt,)? K: Ord + Copy, V> Iterator for $name<$($lt,)? K, V> {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                self.inner.next()
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.inner.size_hint()
+            }
+        }
+
+        impl<$($lt,)? K: Ord + Copy, V> DoubleEndedIterator for $name<$($lt,)? K, V> {
+            fn next_back(&mut self) -> Option<Self::Item> {
+                self.inner.next_back()
+            }
+        }
+
+        impl<$($lt,)? K: Ord + Copy, V> FusedIterator for $name<$($lt,)? K, V> {}
+
+        impl_iterator!(@exact $exact, $name<$($lt,)? K, V>);
+    };
+    (@exact true, $name:ident<$($lt:lifetime,)? K, V>) => {
+        impl<$($lt,)? K: Ord + Copy, V> ExactSizeIterator for $name<$($lt,)? K, V> {
+            fn len(&self) -> usize {
+                self.inner.len()
+            }
+        }
+    };
+    (@exact false, $name:ident<$($lt:lifetime,)? K, V>) => {};
+}
+
+impl_iterator!(Iter<'a, K, V>, (&'a Interval<K>, &'a V), true);
+impl_iterator!(IterMut<'a, K, V>, (&'a Interval<K>, &'a mut V), true);
+impl_iterator!(IntoIter<K, V>, (Interval<K>, V), true);
+impl_iterator!(Keys<'a, K, V>, &'a Interval<K>, true);
+impl_iterator!(Values<'a, K, V>, &'a V, true);
+impl_iterator!(ValuesMut<'a, K, V>, &'a mut V, true);
+impl_iterator!(Range<'a, K, V>, (&'a Interval<K>, &'a V), false);
+
+impl<'a, K: Ord + Copy, V> IntoIterator for &'a IntervalTreeMap<K, V> {
+    type Item = (&'a Interval<K>, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: Ord + Copy, V> IntoIterator for &'a mut IntervalTreeMap<K, V> {
+    type Item = (&'a Interval<K>, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// ```
+/// use ip2c::{Interval, IntervalTreeMap};
+///
+/// let mut map = IntervalTreeMap::new();
+/// let _ = map.insert_scope(0, 4, "a");
+/// let _ = map.insert_scope(5, 9, "b");
+/// let v: Vec<_> = map.into_iter().rev().collect();
+/// assert_eq!(v, [(Interval(5, 9), "b"), (Interval(0, 4), "a")]);
+/// ```
+impl<K: Ord + Copy, V> IntoIterator for IntervalTreeMap<K, V> {
+    type Item = (Interval<K>, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { inner: self.map.into_iter() }
+    }
+}
+
+/// Set-style operations, the results are split at the boundaries of both maps, except the merged [IntervalTreeMap::union].
+impl<K: Discrete, V> IntervalTreeMap<K, V> {
+    /// The intervals covered by either map, `f` combines the values where both cover,
+    /// and the adjacent pieces of the equal values are merged.
+    ///
+    /// ```
+    /// use ip2c::{Interval, IntervalTreeMap};
+    ///
+    /// let mut a = IntervalTreeMap::new();
+    /// let _ = a.insert(Interval(0, 9), 1);
+    /// let mut b = IntervalTreeMap::new();
+    /// let _ = b.insert(Interval(5, 14), 10);
+    /// let c = a.union(&b, |x, y| x + y);
+    /// let v: Vec<_> = c.iter().map(|(k, v)| (*k, *v)).collect();
+    /// assert_eq!(v, [(Interval(0, 4), 1), (Interval(5, 9), 11), (Interval(10, 14), 10)]);
+    ///
+    /// let c = a.union(&b, |_, y| *y);
+    /// assert_eq!(c.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(), [(Interval(0, 4), 1), (Interval(5, 14), 10)]);
+    /// ```
+    pub fn union<F>(&self, other: &IntervalTreeMap<K, V>, mut f: F) -> IntervalTreeMap<K, V>
+        where V: Clone + PartialEq, F: FnMut(&V, &V) -> V {
+        let mut merged: Vec<(Interval<K>, V)> = Vec::new();
+        for (k, a, b) in overlay(self, other) {
+            let v = match (a, b) {
+                (Some(a), Some(b)) => f(a, b),
+                (Some(v), None) | (None, Some(v)) => v.clone(),
+                (None, None) => continue,
+            };
+            match merged.last_mut() {
+                Some((last, x)) if *x == v && last.is_adjacent(&k) => last.1 = k.1,
+                _ => merged.push((k, v)),
+            }
+        }
+        let mut map = IntervalTreeMap::new();
+        map.map.extend(merged);
+        map
+    }
+
+    /// The intervals covered by both maps, `f` combines the values.
+    ///
+    /// ```
+    /// use ip2c::{Interval, IntervalTreeMap};
+    ///
+    /// let mut a = IntervalTreeMap::new();
+    /// let _ = a.insert(Interval(0, 9), "CN");
+    /// let mut b = IntervalTreeMap::new();
+    /// let _ = b.insert(Interval(5, 14), "cdn");
+    /// let c = a.intersection(&b, |x, y| (*x, *y));
+    /// assert_eq!(c.len(), 1);
+    /// assert_eq!(c.get_key_value(7), Some((&Interval(5, 9), &("CN", "cdn"))));
+    /// ```
+    pub fn intersection<W, U, F>(&self, other: &IntervalTreeMap<K, W>, mut f: F) -> IntervalTreeMap<K, U>
+        where F: FnMut(&V, &W) -> U {
+        let mut map = IntervalTreeMap::new();
+        for (k, a, b) in overlay(self, other) {
+            if let (Some(a), Some(b)) = (a, b) {
+                map.map.insert(k, f(a, b));
+            }
+        }
+        map
+    }
+
+    /// The intervals covered by this map but not the other.
+    ///
+    /// ```
+    /// use ip2c::{Interval, IntervalTreeMap};
+    ///
+    /// let mut cn = IntervalTreeMap::new();
+    /// let _ = cn.insert(Interval(0, 9), "CN");
+    /// let mut cdn = IntervalTreeMap::new();
+    /// let _ = cdn.insert(Interval(3, 4), ());
+    /// let c = cn.difference(&cdn);
+    /// assert_eq!(c.keys().copied().collect::<Vec<_>>(), [Interval(0, 2), Interval(5, 9)]);
+    /// ```
+    pub fn difference<W>(&self, other: &IntervalTreeMap<K, W>) -> IntervalTreeMap<K, V>
+        where V: Clone {
+        let mut map = IntervalTreeMap::new();
+        for (k, a, b) in overlay(self, other) {
+            if let (Some(a), None) = (a, b) {
+                map.map.insert(k, a.clone());
+            }
+        }
+        map
+    }
+
+    /// The intervals covered by exactly one of the maps.
+    ///
+    /// ```
+    /// use ip2c::{Interval, IntervalTreeMap};
+    ///
+    /// let mut a = IntervalTreeMap::new();
+    /// let _ = a.insert(Interval(0, 9), 'a');
+    /// let mut b = IntervalTreeMap::new();
+    /// let _ = b.insert(Interval(5, 14), 'b');
+    /// let c = a.symmetric_difference(&b);
+    /// let v: Vec<_> = c.iter().map(|(k, v)| (*k, *v)).collect();
+    /// assert_eq!(v, [(Interval(0, 4), 'a'), (Interval(10, 14), 'b')]);
+    /// ```
+    pub fn symmetric_difference(&self, other: &IntervalTreeMap<K, V>) -> IntervalTreeMap<K, V>
+        where V: Clone {
+        let mut map = IntervalTreeMap::new();
+        for (k, a, b) in overlay(self, other) {
+            match (a, b) {
+                (Some(v), None) | (None, Some(v)) => {
+                    map.map.insert(k, v.clone());
+                }
+                _ => {}
+            }
+        }
+        map
+    }
+}
+
+// split both maps at all the boundaries, and pair the values of each piece in order
+#[allow(clippy::type_complexity)]
+pub(crate) fn overlay<'a, K: Discrete, V, W>(a: &'a IntervalTreeMap<K, V>, b: &'a IntervalTreeMap<K, W>)
+                                  -> Vec<(Interval<K>, Option<&'a V>, Option<&'a W>)> {
+    let mut ia = a.iter().map(|(k, v)| (*k, v));
+    let mut ib = b.iter().map(|(k, v)| (*k, v));
+    let mut ca = ia.next();
+    let mut cb = ib.next();
+    let mut v = Vec::new();
+    loop {
+        match (ca, cb) {
+            (None, None) => break,
+            (Some((x, vx)), None) => {
+                v.push((x, Some(vx), None));
+                ca = ia.next();
+            }
+            (None, Some((y, vy))) => {
+                v.push((y, None, Some(vy)));
+                cb = ib.next();
+            }
+            (Some((x, vx)), Some((y, vy))) => {
+                if x.1 < y.0 {
+                    v.push((x, Some(vx), None));
+                    ca = ia.next();
+                } else if y.1 < x.0 {
+                    v.push((y, None, Some(vy)));
+                    cb = ib.next();
+                } else if x.0 < y.0 {
+                    let (before, rest) = x.split_at(y.0);
+                    v.push((before.unwrap(), Some(vx), None));
+                    ca = rest.map(|r| (r, vx));
+                } else if y.0 < x.0 {
+                    let (before, rest) = y.split_at(x.0);
+                    v.push((before.unwrap(), None, Some(vy)));
+                    cb = rest.map(|r| (r, vy));
+                } else {
+                    let end = x.1.min(y.1);
+                    v.push((Interval(x.0, end), Some(vx), Some(vy)));
+                    ca = match end.next() {
+                        Some(n) if n <= x.1 => Some((Interval(n, x.1), vx)),
+                        _ => ia.next(),
+                    };
+                    cb = match end.next() {
+                        Some(n) if n <= y.1 => Some((Interval(n, y.1), vy)),
+                        _ => ib.next(),
+                    };
+                }
+            }
+        }
+    }
+    v
+}
+
+impl<K: Ord + Copy, V> Default for IntervalTreeMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 
@@ -248,6 +1105,114 @@ mod tests {
         assert_eq!(r, Some(&'A'));
     }
 
+    #[test]
+    fn algebra_bounds() {
+        let all = Interval(u8::MIN, u8::MAX);
+        assert_eq!(all.difference(&Interval(0, 0)), (None, Some(Interval(1, 255))));
+        assert_eq!(all.difference(&Interval(255, 255)), (Some(Interval(0, 254)), None));
+        assert_eq!(all.split_at(255), (Some(Interval(0, 254)), Some(Interval(255, 255))));
+        assert!(!Interval(255u8, 255).is_adjacent(&Interval(0, 0)));
+        assert_eq!(Interval(-3i8, -1).union_if_touching(&Interval(0, 3)), Some(Interval(-3, 3)));
+    }
+
+    #[test]
+    fn set_operations() {
+        let mut a = IntervalTreeMap::new();
+        let _ = a.insert(Interval(0u8, 10), 'a');
+        let _ = a.insert(Interval(20, 30), 'b');
+        let _ = a.insert(Interval(250, 255), 'c');
+        let mut b = IntervalTreeMap::new();
+        let _ = b.insert(Interval(5u8, 25), 'x');
+        let _ = b.insert(Interval(255, 255), 'y');
+        let u = a.union(&b, |_, y| *y);
+        let v: Vec<_> = u.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(v, [
+            (Interval(0, 4), 'a'), (Interval(5, 25), 'x'), (Interval(26, 30), 'b'), (Interval(250, 254), 'c'),
+            (Interval(255, 255), 'y'),
+        ]);
+        // the equal values of both maps are merged too, but not across a gap
+        let mut c = IntervalTreeMap::new();
+        let _ = c.insert(Interval(11u8, 12), 'a');
+        let _ = c.insert(Interval(14, 15), 'a');
+        let u = a.union(&c, |x, _| *x);
+        assert_eq!(u.keys().copied().collect::<Vec<_>>(), [Interval(0, 12), Interval(14, 15), Interval(20, 30), Interval(250, 255)]);
+        let i = a.intersection(&b, |x, y| (*x, *y));
+        assert_eq!(i.len(), 3);
+        assert_eq!(i.query(255), Some(&('c', 'y')));
+        let d = b.difference(&a);
+        assert_eq!(d.keys().copied().collect::<Vec<_>>(), [Interval(11, 19)]);
+        let s = a.symmetric_difference(&b);
+        assert_eq!(s.len(), 4);
+        assert_eq!(s.query(255), None);
+    }
+
+    #[test]
+    fn entry() {
+        use crate::{Entry, IntervalError};
+
+        let mut map = IntervalTreeMap::new();
+        let _ = map.insert(Interval(10, 20), 1);
+        let _ = map.insert(Interval(30, 40), 2);
+        assert!(matches!(map.entry(Interval(21, 29)), Ok(Entry::Vacant(_))));
+        assert!(matches!(map.entry(Interval(10, 20)), Ok(Entry::Occupied(_))));
+        assert!(matches!(map.entry(Interval(15, 35)), Ok(Entry::Conflicting(_))));
+        let _ = map.entry(Interval(10, 20)).unwrap().and_modify(|v| *v += 10);
+        assert_eq!(map.query(10), Some(&11));
+        if let Ok(Entry::Conflicting(e)) = map.entry(Interval(35, 50)) {
+            assert_eq!(e.remove_entry(), (Interval(30, 40), 2));
+        }
+        assert_eq!(map.entry(Interval(35, 50)).unwrap().or_insert(3), Ok(&mut 3));
+        assert_eq!(map.len(), 2);
+        if let Ok(Entry::Occupied(e)) = map.entry(Interval(35, 50)) {
+            assert_eq!(e.remove(), 3);
+        }
+        assert_eq!(map.len(), 1);
+        assert!(matches!(map.entry(Interval(50, 35)), Err(IntervalError::Invalid(_))));
+    }
+
+    #[test]
+    fn remove_range() {
+        let mut map = IntervalTreeMap::new();
+        let _ = map.insert(Interval(0u8, 255), 'a');
+        assert_eq!(map.remove_range(Interval(10, 20)), vec![(Interval(10, 20), 'a')]);
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), [Interval(0, 9), Interval(21, 255)]);
+        assert!(map.remove_range(Interval(20, 10)).is_empty());
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.remove_range(Interval(0, 255)).len(), 2);
+        assert!(map.is_empty());
+        assert!(map.remove_range(Interval(0, 0)).is_empty());
+    }
+
+    #[test]
+    fn iterators() {
+        let mut map = IntervalTreeMap::new();
+        let _ = map.insert(Interval(20, 29), 2);
+        let _ = map.insert(Interval(0, 9), 0);
+        let _ = map.insert(Interval(10, 19), 1);
+        assert_eq!(map.iter().len(), 3);
+        assert_eq!(map.keys().map(|k| k.0).collect::<Vec<_>>(), [0, 10, 20]);
+        assert_eq!(map.values().rev().copied().collect::<Vec<_>>(), [2, 1, 0]);
+        for v in map.values_mut() {
+            *v += 1;
+        }
+        for (k, v) in map.iter_mut() {
+            *v *= k.0 + 1;
+        }
+        for (_, v) in &mut map {
+            *v += 1;
+        }
+        assert_eq!((&map).into_iter().map(|(_, v)| *v).collect::<Vec<_>>(), [2, 23, 64]);
+        let mut range = map.range(Interval(5, 25));
+        assert_eq!(range.next_back(), Some((&Interval(20, 29), &64)));
+        assert_eq!(range.next(), Some((&Interval(0, 9), &2)));
+        assert_eq!(range.next(), Some((&Interval(10, 19), &23)));
+        assert_eq!(range.next(), None);
+        let mut owned = map.into_iter();
+        assert_eq!(owned.len(), 3);
+        assert_eq!(owned.next(), Some((Interval(0, 9), 2)));
+        assert_eq!(owned.count(), 2);
+    }
+
     #[test]
     fn remove() {
         let mut map = IntervalTreeMap::new();
@@ -13,6 +13,20 @@
 //! println!("{}", code.unwrap());
 //! ```
 //!
+//! Private, loopback, documentation and other reserved blocks are never delegated to a country,
+//! use [special] to look them up from the compiled-in IANA special-purpose registries.
+//!
+//! Use [export] to generate ipset, nftables and iptables rules of some countries and regions,
+//! or the nginx, HAProxy and Apache country mapping.
+//!
+//! Use [formats] to import the GeoLite2, DB-IP and IP2Location country CSV databases instead of the delegated files.
+//!
+//! Enable the `serde` feature to serialize and deserialize the addresses, intervals and trees.
+//!
+//! Enable the `shared` feature for the hot-reloadable `rir::shared::Shared` maps, eg. `SharedIpCodeMap`, and `watch` to reload it on file changes.
+//!
+//! Enable the `server` feature for the lookup services, see `server` and the `ip2c-server` binary.
+//!
 //! Also, you can use [IpTree] to build IP city location map, eg.
 //! ```
 //! use ip2c::IpTree;
@@ -30,9 +44,20 @@
 
 pub mod itree;
 mod ip2c;
+mod cidr;
 pub mod util;
 pub mod rir;
+pub mod special;
+pub mod stats;
+pub mod export;
+pub mod formats;
+pub mod cli;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "serde")]
+mod serde_impl;
 
 
 pub use crate::itree::*;
 pub use crate::ip2c::*;
+pub use crate::cidr::*;
@@ -0,0 +1,176 @@
+use std::fmt::{Display, Formatter, Write};
+
+use crate::{Discrete, Interval, IntervalTreeMap};
+use crate::itree::overlay;
+use crate::rir::*;
+use crate::util::json_str;
+
+/// One changed range between two snapshots
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Change<K: Ord + Copy> {
+    Added { range: Interval<K>, code: CountryRegionCode },
+    Removed { range: Interval<K>, code: CountryRegionCode },
+    Reassigned { range: Interval<K>, old: CountryRegionCode, new: CountryRegionCode },
+}
+
+impl<K: Ord + Copy> Change<K> {
+    pub fn range(&self) -> Interval<K> {
+        match self {
+            Change::Added { range, .. } | Change::Removed { range, .. } | Change::Reassigned { range, .. } => *range,
+        }
+    }
+}
+
+impl<K: Ord + Copy + Display> Display for Change<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let range = self.range();
+        match self {
+            Change::Added { code, .. } => f.write_fmt(format_args!("+ {}-{} {}", range.0, range.1, code)),
+            Change::Removed { code, .. } => f.write_fmt(format_args!("- {}-{} {}", range.0, range.1, code)),
+            Change::Reassigned { old, new, .. } => f.write_fmt(format_args!("~ {}-{} {} -> {}", range.0, range.1, old, new)),
+        }
+    }
+}
+
+/// The changes between two [IpCodeMap]s, see [diff]
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct RirDiff {
+    pub ipv4: Vec<Change<IPv4>>,
+    pub ipv6: Vec<Change<IPv6>>,
+}
+
+/// Compare two snapshots, eg. yesterday's and today's delegated files.
+///
+/// The ranges are split at the boundaries of both snapshots, and the adjacent same changes are merged.
+///
+/// ```
+/// use ip2c::rir::{CountryRegionCode, IpCodeMap};
+/// use ip2c::rir::diff::diff;
+///
+/// let cn = CountryRegionCode::new("CN").unwrap();
+/// let jp = CountryRegionCode::new("JP").unwrap();
+/// let mut old = IpCodeMap::new();
+/// old.ipv4.insert("1.0.0.0/24".parse().unwrap(), cn).unwrap();
+/// let mut new = IpCodeMap::new();
+/// new.ipv4.insert("1.0.0.0/25".parse().unwrap(), cn).unwrap();
+/// new.ipv4.insert("1.0.0.128/25".parse().unwrap(), jp).unwrap();
+/// new.ipv6.insert("2001:db8::/32".parse().unwrap(), jp).unwrap();
+///
+/// let d = diff(&old, &new);
+/// assert_eq!(d.to_string(), "~ 1.0.0.128-1.0.0.255 CN -> JP\n+ 2001:db8::-2001:db8:ffff:ffff:ffff:ffff:ffff:ffff JP\n");
+/// assert!(d.to_json().starts_with(r#"{"ipv4":[{"change":"reassigned","start":"1.0.0.128","end":"1.0.0.255","old":"CN","new":"JP"}]"#));
+/// ```
+pub fn diff(old: &IpCodeMap, new: &IpCodeMap) -> RirDiff {
+    RirDiff {
+        ipv4: diff_tree(&old.ipv4, &new.ipv4),
+        ipv6: diff_tree(&old.ipv6, &new.ipv6),
+    }
+}
+
+fn diff_tree<K: Discrete>(old: &IntervalTreeMap<K, CountryRegionCode>, new: &IntervalTreeMap<K, CountryRegionCode>) -> Vec<Change<K>> {
+    let mut v: Vec<Change<K>> = Vec::new();
+    for (range, a, b) in overlay(old, new) {
+        let change = match (a, b) {
+            (Some(old), Some(new)) if old != new => Change::Reassigned { range, old: *old, new: *new },
+            (Some(code), None) => Change::Removed { range, code: *code },
+            (None, Some(code)) => Change::Added { range, code: *code },
+            _ => continue,
+        };
+        if let Some(last) = v.last_mut() {
+            if let Some(merged) = merge(last, &change) {
+                *last = merged;
+                continue;
+            }
+        }
+        v.push(change);
+    }
+    v
+}
+
+// merge two adjacent changes of the same kind and codes
+fn merge<K: Discrete>(a: &Change<K>, b: &Change<K>) -> Option<Change<K>> {
+    // the pieces never overlap, so touching means adjacent
+    let range = a.range().union_if_touching(&b.range())?;
+    match (a, b) {
+        (Change::Added { code: x, .. }, Change::Added { code: y, .. }) if x == y => Some(Change::Added { range, code: *x }),
+        (Change::Removed { code: x, .. }, Change::Removed { code: y, .. }) if x == y => Some(Change::Removed { range, code: *x }),
+        (Change::Reassigned { old: o1, new: n1, .. }, Change::Reassigned { old: o2, new: n2, .. }) if o1 == o2 && n1 == n2 => {
+            Some(Change::Reassigned { range, old: *o1, new: *n1 })
+        }
+        _ => None,
+    }
+}
+
+impl RirDiff {
+    pub fn is_empty(&self) -> bool {
+        self.ipv4.is_empty() && self.ipv6.is_empty()
+    }
+
+    /// The JSON report, eg. `{"ipv4":[{"change":"added","start":"1.0.0.0","end":"1.0.0.255","code":"CN"}],"ipv6":[]}`
+    pub fn to_json(&self) -> String {
+        let mut s = String::from("{\"ipv4\":[");
+        write_json_list(&mut s, &self.ipv4);
+        s.push_str("],\"ipv6\":[");
+        write_json_list(&mut s, &self.ipv6);
+        s.push_str("]}");
+        s
+    }
+}
+
+/// The text report, one change a line: `+` added, `-` removed and `~` reassigned
+impl Display for RirDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for c in &self.ipv4 {
+            writeln!(f, "{}", c)?;
+        }
+        for c in &self.ipv6 {
+            writeln!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_json_list<K: Ord + Copy + Display>(s: &mut String, list: &[Change<K>]) {
+    for (i, c) in list.iter().enumerate() {
+        if i > 0 {
+            s.push(',');
+        }
+        let range = c.range();
+        let _ = match c {
+            Change::Added { code, .. } => write!(s, "{{\"change\":\"added\",\"start\":\"{}\",\"end\":\"{}\",\"code\":{}}}",
+                                                 range.0, range.1, json_str(code.name())),
+            Change::Removed { code, .. } => write!(s, "{{\"change\":\"removed\",\"start\":\"{}\",\"end\":\"{}\",\"code\":{}}}",
+                                                   range.0, range.1, json_str(code.name())),
+            Change::Reassigned { old, new, .. } => write!(s, "{{\"change\":\"reassigned\",\"start\":\"{}\",\"end\":\"{}\",\"old\":{},\"new\":{}}}",
+                                                          range.0, range.1, json_str(old.name()), json_str(new.name())),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rir::{CountryRegionCode, IpCodeMap};
+    use super::{diff, Change};
+
+    #[test]
+    fn diff_snapshots() {
+        let cn = CountryRegionCode::new("CN").unwrap();
+        let us = CountryRegionCode::new("US").unwrap();
+        let mut old = IpCodeMap::new();
+        old.ipv4.insert("1.0.0.0-1.0.0.255".parse().unwrap(), cn).unwrap();
+        old.ipv4.insert("2.0.0.0/24".parse().unwrap(), us).unwrap();
+        let mut new = IpCodeMap::new();
+        new.ipv4.insert("1.0.0.0-1.0.0.99".parse().unwrap(), cn).unwrap();
+        new.ipv4.insert("1.0.0.100-1.0.0.255".parse().unwrap(), cn).unwrap();
+        new.ipv4.insert("1.0.1.0/24".parse().unwrap(), us).unwrap();
+        new.ipv4.insert("1.0.2.0/24".parse().unwrap(), us).unwrap();
+        let d = diff(&old, &new);
+        assert_eq!(d.ipv4, [
+            Change::Added { range: "1.0.1.0-1.0.2.255".parse().unwrap(), code: us },
+            Change::Removed { range: "2.0.0.0/24".parse().unwrap(), code: us },
+        ]);
+        assert!(d.ipv6.is_empty());
+        assert!(diff(&new, &new).is_empty());
+        assert_eq!(diff(&IpCodeMap::new(), &IpCodeMap::new()).to_json(), r#"{"ipv4":[],"ipv6":[]}"#);
+    }
+}
@@ -1,10 +1,15 @@
 pub mod parse;
+pub mod diff;
+#[cfg(feature = "shared")]
+pub mod shared;
+#[cfg(feature = "watch")]
+pub mod watch;
 mod test;
 
 use crate::ip2c::*;
 
 /// Codes for the representation of names of countries and regions
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct CountryRegionCode {
     raw: [u8; 2],
 }
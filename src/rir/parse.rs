@@ -1,17 +1,22 @@
 use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::io;
 use std::io::BufRead;
 use std::fs;
+use std::net::IpAddr;
 use std::path::Path;
 
-use crate::Interval;
+use crate::{Interval, IntervalTreeMap};
 use crate::rir::*;
 
 pub enum IpRange {
     Ipv4(Interval<IPv4>),
     Ipv6(Interval<IPv6>),
+    /// the autonomous system numbers
+    Asn(Interval<u32>),
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum IpState {
     Assigned,
     Allocated,
@@ -20,18 +25,114 @@ pub enum IpState {
     Unknown,
 }
 
+impl IpState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IpState::Assigned => "assigned",
+            IpState::Allocated => "allocated",
+            IpState::Reserved => "reserved",
+            IpState::Available => "available",
+            IpState::Unknown => "unknown",
+        }
+    }
+}
+
+impl Display for IpState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One line of the delegated file:
+/// `registry|cc|type|start|value|date|status[|opaque-id[|extensions...]]`
 pub struct Entity {
     pub range: IpRange,
     pub state: IpState,
     pub code: CountryRegionCode,
+    pub registry: String,
+    /// `yyyymmdd`, empty if unknown
+    pub date: String,
+    /// only in the extended format
+    pub opaque_id: Option<String>,
+}
+
+/// The delegated record of a range, the value of [RecordMap]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub registry: String,
+    pub code: CountryRegionCode,
+    pub state: IpState,
+    pub date: String,
+    pub opaque_id: Option<String>,
+}
+
+/// Like [IpCodeMap], but keeps the whole delegated records
+///
+/// ```
+/// use ip2c::rir::parse::{IpState, RecordMap};
+///
+/// let mut map = RecordMap::new();
+/// map.load_from_file("./data/example.txt").unwrap();
+/// let r = map.query("10.1.2.3".parse().unwrap()).unwrap();
+/// assert_eq!(r.code.name(), "--");
+/// assert_eq!(r.state, IpState::Unknown);
+/// ```
+pub type RecordMap = IpTree<Record>;
+
+/// The delegated records of the autonomous system numbers
+///
+/// ```
+/// use ip2c::rir::parse::{parse_line, AsnMap};
+///
+/// let mut map = AsnMap::new();
+/// map.add_entity(parse_line("apnic|CN|asn|4134|1|20021210|allocated|A92E1062").unwrap()).unwrap();
+/// assert_eq!(map.query(4134).unwrap().opaque_id.as_deref(), Some("A92E1062"));
+/// ```
+pub type AsnMap = IntervalTreeMap<u32, Record>;
+
+/// All the delegated records, of the addresses and the autonomous system numbers, loaded in one pass
+///
+/// ```
+/// use ip2c::rir::parse::Delegated;
+///
+/// let mut db = Delegated::default();
+/// db.load_from_dir("./data").unwrap();
+/// assert_eq!(db.records.query("10.1.2.3".parse().unwrap()).unwrap().code.name(), "--");
+/// assert!(db.asn.is_empty());
+/// ```
+#[derive(Default)]
+pub struct Delegated {
+    pub records: RecordMap,
+    pub asn: AsnMap,
 }
 
+/// The maps filled by the entities of the delegated files, which can be loaded as a whole,
+/// eg. by [Shared](crate::rir::shared::Shared)
+pub trait AddEntity: Default {
+    fn add_entity(&mut self, entity: Entity) -> Result<(), Box<dyn Error>>;
+
+    /// A new map of the delegated files in the directory
+    fn from_dir(dir_path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut map = Self::default();
+        for_each_file(dir_path, |path| for_each_entity(path, |entity| map.add_entity(entity)))?;
+        Ok(map)
+    }
+
+    /// Like [AddEntity::from_dir], but every file is read once, validated like [validate_file],
+    /// and parsed from the same content only if it is valid.
+    fn from_checked_dir(dir_path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut map = Self::default();
+        for_each_file(dir_path, |path| for_each_checked_entity(path, |entity| map.add_entity(entity)))?;
+        Ok(map)
+    }
+}
 
-pub fn parse_line(line: &String) -> Option<Entity> {
+pub fn parse_line(line: &str) -> Option<Entity> {
     if line.starts_with('#') {
         None?
     }
-    let slices = line.splitn(8, "|");
+    let line = line.trim_end_matches(['\r', '\n']);
+    let slices = line.splitn(9, "|");
     let mut sl = [""; 8];
     let mut i = 0;
     for s in slices {
@@ -56,6 +157,7 @@ pub fn parse_line(line: &String) -> Option<Entity> {
     let range = match sl[2] {
         "ipv4" => IpRange::Ipv4(parse_ipv4_range(sl[3], sl[4]).ok()?),
         "ipv6" => IpRange::Ipv6(parse_ipv6_range(sl[3], sl[4]).ok()?),
+        "asn" => IpRange::Asn(parse_asn_range(sl[3], sl[4]).ok()?),
         _ => None?
     };
 
@@ -63,6 +165,9 @@ pub fn parse_line(line: &String) -> Option<Entity> {
         range,
         state,
         code,
+        registry: sl[0].to_string(),
+        date: sl[5].to_string(),
+        opaque_id: if i > 7 { Some(sl[7].to_string()) } else { None },
     })
 }
 
@@ -72,10 +177,104 @@ fn parse_ipv4_range(ip_str: &str, add: &str) -> Result<Interval<IPv4>, Box<dyn E
     Ok(Interval(ip, ip.0.wrapping_add(add - 1).into()))
 }
 
+fn parse_asn_range(asn: &str, count: &str) -> Result<Interval<u32>, Box<dyn Error>> {
+    let asn: u32 = asn.parse()?;
+    let count: u32 = count.parse()?;
+    let last = count.checked_sub(1).and_then(|n| asn.checked_add(n)).ok_or("invalid asn count")?;
+    Ok(Interval(asn, last))
+}
+
+// the value of ipv6 is the prefix length, not the number of addresses
 fn parse_ipv6_range(ip_str: &str, mask: &str) -> Result<Interval<IPv6>, Box<dyn Error>> {
     let ip: IPv6 = ip_str.parse()?;
     let mask: u8 = mask.parse()?;
-    Ok(Interval(ip, ip.0.wrapping_add((1 << mask) - 1).into()))
+    let host = match mask {
+        0 => u128::MAX,
+        1..=128 => (1 << (128 - mask)) - 1,
+        _ => Err(format!("invalid prefix length: {}", mask))?,
+    };
+    Ok(Interval(ip, ip.0.wrapping_add(host).into()))
+}
+
+/// The version line and the number of records of a delegated file, see [summarize_file]
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct FileSummary {
+    /// the fields of the version line, empty if there is no version line
+    pub registry: String,
+    pub serial: String,
+    pub start_date: String,
+    pub end_date: String,
+    /// the number of records in the version line
+    pub expected_records: Option<usize>,
+    /// the number of record lines, the summary lines are not counted
+    pub records: usize,
+}
+
+/// Reads the version line and counts the records of the delegated file.
+///
+/// ```
+/// use ip2c::rir::parse::summarize_file;
+///
+/// let s = summarize_file("./data/example.txt").unwrap();
+/// assert_eq!(s.records, 3);
+/// assert_eq!(s.expected_records, None);
+/// ```
+pub fn summarize_file(file_path: impl AsRef<Path>) -> Result<FileSummary, Box<dyn Error>> {
+    let file_path = file_path.as_ref();
+    let content = fs::read_to_string(file_path)?;
+    summarize(file_path, &content)
+}
+
+fn summarize(file_path: &Path, content: &str) -> Result<FileSummary, Box<dyn Error>> {
+    let mut summary = FileSummary::default();
+    let mut has_version = false;
+    for line in content.lines() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        let first = line.split('|').next().unwrap_or_default();
+        if !has_version && summary.records == 0 && !first.is_empty() && first.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            // version|registry|serial|records|startdate|enddate|UTCoffset
+            let fields: Vec<&str> = line.split('|').collect();
+            let records = fields.get(3).and_then(|n| n.parse::<usize>().ok())
+                .ok_or_else(|| format!("{}: invalid version line", file_path.display()))?;
+            let field = |i: usize| fields.get(i).unwrap_or(&"").to_string();
+            summary.registry = field(1);
+            summary.serial = field(2);
+            summary.start_date = field(4);
+            summary.end_date = field(5);
+            summary.expected_records = Some(records);
+            has_version = true;
+            continue;
+        }
+        if line.ends_with("|summary") {
+            continue;
+        }
+        summary.records += 1;
+    }
+    Ok(summary)
+}
+
+/// Checks the delegated file is completely written, returns the number of records.
+///
+/// The version line, eg. `2|apnic|20230313|80123|19830613|20230310|+1000`, is required and the number of records
+/// must equal to it, and the last line must be terminated.
+pub fn validate_file(file_path: impl AsRef<Path>) -> Result<usize, Box<dyn Error>> {
+    let file_path = file_path.as_ref();
+    let content = fs::read_to_string(file_path)?;
+    validate(file_path, &content)
+}
+
+fn validate(file_path: &Path, content: &str) -> Result<usize, Box<dyn Error>> {
+    if !content.is_empty() && !content.ends_with('\n') {
+        Err(format!("{}: the last line is not terminated", file_path.display()))?
+    }
+    let summary = summarize(file_path, content)?;
+    match summary.expected_records {
+        None => Err(format!("{}: no version line", file_path.display()).into()),
+        Some(n) if n != summary.records => Err(format!("{}: expect {} records, but {} found", file_path.display(), n, summary.records).into()),
+        Some(_) => Ok(summary.records),
+    }
 }
 
 impl IpCodeMap {
@@ -83,47 +282,131 @@ impl IpCodeMap {
         match entity.range {
             IpRange::Ipv4(k) => self.ipv4.insert(k, entity.code)?,
             IpRange::Ipv6(k) => self.ipv6.insert(k, entity.code)?,
+            IpRange::Asn(_) => {}
         }
         Ok(())
     }
 
     pub fn load_from_dir(&mut self, dir_path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
-        let dir = fs::read_dir(dir_path)?;
-        for f in dir {
-            match f {
-                Err(e) => return Err(Box::new(e)),
-                Ok(f) => {
-                    if !f.file_type()?.is_dir() {
-                        if let Some(filename) = f.file_name().to_str() {
-                            // if !filename.ends_with(".txt") {
-                            //     continue
-                            // }
-                            println!("loading {}", filename);
-                            self.load_from_file(f.path())?;
-                        }
-                    }
-                }
-            }
+        for_each_file(dir_path, |path| self.load_from_file(path))
+    }
+
+    pub fn load_from_file(&mut self, file_path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        for_each_entity(file_path, |entity| self.add_entity(entity))
+    }
+}
+
+impl AddEntity for IpCodeMap {
+    fn add_entity(&mut self, entity: Entity) -> Result<(), Box<dyn Error>> {
+        IpCodeMap::add_entity(self, entity)
+    }
+}
+
+impl RecordMap {
+    pub fn add_entity(&mut self, entity: Entity) -> Result<(), Box<dyn Error>> {
+        let Entity { range, state, code, registry, date, opaque_id } = entity;
+        let record = Record { registry, code, state, date, opaque_id };
+        match range {
+            IpRange::Ipv4(k) => self.ipv4.insert(k, record)?,
+            IpRange::Ipv6(k) => self.ipv6.insert(k, record)?,
+            IpRange::Asn(_) => {}
         }
         Ok(())
     }
 
+    pub fn load_from_dir(&mut self, dir_path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        for_each_file(dir_path, |path| self.load_from_file(path))
+    }
+
     pub fn load_from_file(&mut self, file_path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
-        let f = fs::File::open(file_path)?;
-        let mut r = io::BufReader::new(f);
-        let mut buf = String::new();
-        // let mut index = 0;
-        loop {
-            // index += 1;
-            let line = r.read_line(&mut buf)?;
-            if line == 0 {
-                break;
-            }
-            if let Some(entity) = parse_line(&buf) {
-                self.add_entity(entity)?
-            }
-            buf.clear();
+        for_each_entity(file_path, |entity| self.add_entity(entity))
+    }
+
+    /// query [Record] of ip
+    pub fn query(&self, ip: IpAddr) -> Option<&Record> {
+        match ip {
+            IpAddr::V4(ip) => self.ipv4.query(ip.into()),
+            IpAddr::V6(ip) => self.ipv6.query(ip.into()),
+        }
+    }
+}
+
+impl AsnMap {
+    pub fn add_entity(&mut self, entity: Entity) -> Result<(), Box<dyn Error>> {
+        let Entity { range, state, code, registry, date, opaque_id } = entity;
+        if let IpRange::Asn(k) = range {
+            self.insert(k, Record { registry, code, state, date, opaque_id })?;
         }
         Ok(())
     }
+
+    pub fn load_from_dir(&mut self, dir_path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        for_each_file(dir_path, |path| self.load_from_file(path))
+    }
+
+    pub fn load_from_file(&mut self, file_path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        for_each_entity(file_path, |entity| self.add_entity(entity))
+    }
+}
+
+impl Delegated {
+    pub fn add_entity(&mut self, entity: Entity) -> Result<(), Box<dyn Error>> {
+        match entity.range {
+            IpRange::Asn(_) => self.asn.add_entity(entity),
+            _ => self.records.add_entity(entity),
+        }
+    }
+
+    pub fn load_from_dir(&mut self, dir_path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        for_each_file(dir_path, |path| self.load_from_file(path))
+    }
+
+    pub fn load_from_file(&mut self, file_path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        for_each_entity(file_path, |entity| self.add_entity(entity))
+    }
+}
+
+impl AddEntity for Delegated {
+    fn add_entity(&mut self, entity: Entity) -> Result<(), Box<dyn Error>> {
+        Delegated::add_entity(self, entity)
+    }
+}
+
+fn for_each_file(dir_path: impl AsRef<Path>, mut f: impl FnMut(&Path) -> Result<(), Box<dyn Error>>) -> Result<(), Box<dyn Error>> {
+    let dir = fs::read_dir(dir_path)?;
+    for entry in dir {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            f(&entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+fn for_each_checked_entity(file_path: &Path, mut f: impl FnMut(Entity) -> Result<(), Box<dyn Error>>) -> Result<(), Box<dyn Error>> {
+    let content = fs::read_to_string(file_path)?;
+    validate(file_path, &content)?;
+    for line in content.lines() {
+        if let Some(entity) = parse_line(line) {
+            f(entity)?
+        }
+    }
+    Ok(())
+}
+
+fn for_each_entity(file_path: impl AsRef<Path>, mut f: impl FnMut(Entity) -> Result<(), Box<dyn Error>>) -> Result<(), Box<dyn Error>> {
+    let file = fs::File::open(file_path)?;
+    let mut r = io::BufReader::new(file);
+    let mut buf = String::new();
+    loop {
+        let line = r.read_line(&mut buf)?;
+        if line == 0 {
+            break;
+        }
+        if let Some(entity) = parse_line(&buf) {
+            f(entity)?
+        }
+        buf.clear();
+    }
+    Ok(())
 }
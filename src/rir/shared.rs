@@ -0,0 +1,145 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::SystemTime;
+
+use arc_swap::ArcSwap;
+
+use crate::rir::*;
+use crate::rir::parse::AddEntity;
+
+/// One published map of [Shared]
+pub struct Snapshot<T = IpCodeMap> {
+    pub map: T,
+    /// starts from `1`, increased by every successful reload
+    pub generation: u64,
+    pub loaded_at: SystemTime,
+}
+
+/// A thread-safe handle of a map, eg. [IpCodeMap] or [Delegated](crate::rir::parse::Delegated),
+/// which can be replaced without downtime.
+///
+/// The reads are lock-free, and a reload publishes the new map atomically only if loading succeeded,
+/// the old map is kept on failure. Clone the handle to share it between threads.
+///
+/// ```
+/// use ip2c::rir::shared::SharedIpCodeMap;
+///
+/// let map = SharedIpCodeMap::load_from_dir("./data").expect("load rir txt info failed");
+/// assert_eq!(map.generation(), 1);
+/// assert!(map.query("127.0.0.1".parse().unwrap()).is_some());
+///
+/// let generation = map.reload_from_dir("./data").unwrap();
+/// assert_eq!(generation, 2);
+/// assert!(map.reload_from_dir("./no-such-dir").is_err());
+/// assert_eq!(map.generation(), 2);
+/// ```
+pub struct Shared<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// The shared [IpCodeMap]
+pub type SharedIpCodeMap = Shared<IpCodeMap>;
+
+struct Inner<T> {
+    current: ArcSwap<Snapshot<T>>,
+    // serialize the publishing, so the generation is increasing
+    publish: Mutex<()>,
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Shared { inner: self.inner.clone() }
+    }
+}
+
+impl SharedIpCodeMap {
+    /// query [CountryRegionCode] of ip from the current snapshot
+    pub fn query(&self, ip: IpAddr) -> Option<CountryRegionCode> {
+        self.inner.current.load().map.query(ip)
+    }
+}
+
+impl<T> Shared<T> {
+    pub fn new(map: T) -> Self {
+        let snapshot = Snapshot { map, generation: 1, loaded_at: SystemTime::now() };
+        Shared {
+            inner: Arc::new(Inner {
+                current: ArcSwap::from_pointee(snapshot),
+                publish: Mutex::new(()),
+            })
+        }
+    }
+
+    /// The current snapshot, it stays valid even if a reload happens later.
+    pub fn snapshot(&self) -> Arc<Snapshot<T>> {
+        self.inner.current.load_full()
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.inner.current.load().generation
+    }
+
+    pub fn loaded_at(&self) -> SystemTime {
+        self.inner.current.load().loaded_at
+    }
+
+    /// Publishes the map as a new snapshot, returns its generation.
+    pub fn replace(&self, map: T) -> u64 {
+        let _guard = self.inner.publish.lock().unwrap_or_else(|e| e.into_inner());
+        let generation = self.inner.current.load().generation + 1;
+        self.inner.current.store(Arc::new(Snapshot { map, generation, loaded_at: SystemTime::now() }));
+        generation
+    }
+}
+
+impl<T: AddEntity> Shared<T> {
+    pub fn load_from_dir(dir_path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::new(T::from_dir(dir_path.as_ref())?))
+    }
+
+    /// Loads a new map from the directory and publishes it, returns the new generation.
+    /// The current map is kept if loading failed.
+    pub fn reload_from_dir(&self, dir_path: impl AsRef<Path>) -> Result<u64, Box<dyn Error>> {
+        Ok(self.replace(T::from_dir(dir_path.as_ref())?))
+    }
+}
+
+impl<T: AddEntity + Send + Sync + 'static> Shared<T> {
+    /// Like [Shared::reload_from_dir], but loads in a background thread.
+    ///
+    /// ```
+    /// use ip2c::rir::shared::SharedIpCodeMap;
+    /// use ip2c::rir::IpCodeMap;
+    ///
+    /// let map = SharedIpCodeMap::new(IpCodeMap::new());
+    /// let handle = map.reload_in_background("./data");
+    /// assert_eq!(handle.join().unwrap().unwrap(), 2);
+    /// assert!(map.query("127.0.0.1".parse().unwrap()).is_some());
+    /// ```
+    pub fn reload_in_background(&self, dir_path: impl Into<PathBuf>) -> JoinHandle<Result<u64, ReloadError>> {
+        let this = self.clone();
+        let dir_path = dir_path.into();
+        std::thread::spawn(move || {
+            this.reload_from_dir(&dir_path).map_err(|e| ReloadError { path: dir_path, message: e.to_string() })
+        })
+    }
+}
+
+/// The error of [Shared::reload_in_background]
+#[derive(Debug, Clone)]
+pub struct ReloadError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl Display for ReloadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("reload {} failed: {}", self.path.display(), self.message))
+    }
+}
+
+impl Error for ReloadError {}
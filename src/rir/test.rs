@@ -8,24 +8,69 @@ mod tests {
     fn parse_eg_data() {
         let mut map = IpCodeMap::new();
         println!("load data...");
-        let _ = map.load_from_dir("./data").expect("dirty data");
+        map.load_from_dir("./data").expect("dirty data");
         println!("unknown_ipv4_segments:");
         show_unknown_ipv4_segments(&map.ipv4);
         println!("known_ipv6_code:");
         show_known_ipv6_code(&map.ipv6);
         let r = map.query("127.0.0.1".parse().unwrap());
-        assert_eq!(r.is_some(), true);
-        assert_eq!(map.ipv4.len() > 0, true);
-        assert_eq!(map.ipv6.len() > 0, true);
+        assert!(r.is_some());
+        assert!(!map.ipv4.is_empty());
+        assert!(!map.ipv6.is_empty());
+    }
+
+    #[test]
+    fn parse_ipv6_prefix_length() {
+        use crate::rir::parse::{parse_line, IpRange};
+
+        let range = |line: &str| match parse_line(line).map(|e| e.range) {
+            Some(IpRange::Ipv6(k)) => Some(k),
+            _ => None,
+        };
+        let prefix = |s: &str| s.parse::<Interval<IPv6>>().ok();
+        assert_eq!(range("apnic|CN|ipv6|2001:250::|35|20000426|allocated"), prefix("2001:250::/35"));
+        assert_eq!(range("iana|--|ipv6|::|0|20000101|reserved"), prefix("::/0"));
+        assert_eq!(range("apnic|JP|ipv6|2001:200::1|128|20000101|assigned"), prefix("2001:200::1/128"));
+        assert_eq!(range("apnic|JP|ipv6|2001:200::|129|20000101|assigned"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "shared")]
+    fn shared_reload() {
+        use crate::rir::shared::SharedIpCodeMap;
+
+        let shared = SharedIpCodeMap::new(IpCodeMap::new());
+        let old = shared.snapshot();
+        let readers: Vec<_> = (0..4).map(|_| {
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                let mut last = 0;
+                for _ in 0..1000 {
+                    let generation = shared.generation();
+                    assert!(generation >= last);
+                    last = generation;
+                }
+            })
+        }).collect();
+        for _ in 0..10 {
+            shared.reload_from_dir("./data").expect("dirty data");
+        }
+        for r in readers {
+            r.join().unwrap();
+        }
+        assert_eq!(shared.generation(), 11);
+        assert!(shared.query("10.1.1.1".parse().unwrap()).is_some());
+        assert_eq!(old.generation, 1);
+        assert!(old.map.query("10.1.1.1".parse().unwrap()).is_none());
     }
 
     fn show_unknown_ipv4_segments(tree: &Ipv4Tree<CountryRegionCode>) {
         let mut has_pre = false;
         let mut pre_y = 0;
         let mut not_included = Vec::new();
-        for (k, _v) in tree.tree() {
+        for k in tree.tree().keys() {
             let (x, y) = (k.0.0, k.1.0);
-            assert_eq!(!has_pre || pre_y < x, true);
+            assert!(!has_pre || pre_y < x);
             if has_pre {
                 if pre_y < x - 1 {
                     not_included.push(Interval(IPv4(pre_y + 1), IPv4(x - 1)))
@@ -0,0 +1,232 @@
+//! Reload a [Shared] map when the delegated files in its directory change, enabled by the `watch` feature.
+
+use std::error::Error;
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask, Watches};
+
+use crate::rir::*;
+use crate::rir::parse::AddEntity;
+use crate::rir::shared::Shared;
+
+/// The extra check of a loaded map before it is published, eg. a minimal number of ranges
+pub type Validator<T = IpCodeMap> = Box<dyn Fn(&T) -> Result<(), String> + Send>;
+
+/// Watches the directory of delegated files (inotify based), and reloads the [Shared] map when a file is
+/// written, replaced, renamed into place or removed.
+///
+/// The reload waits until the directory is quiet for the debounce duration, then every file is read once, validated
+/// (see [validate_file](crate::rir::parse::validate_file)) and parsed from the same content, so a half-written file never replaces a good map.
+/// The watcher thread sleeps until the next event, and stops when the watcher is dropped.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use ip2c::rir::shared::SharedIpCodeMap;
+/// use ip2c::rir::watch::DirWatcher;
+///
+/// let map = SharedIpCodeMap::load_from_dir("./data").unwrap();
+/// let _watcher = DirWatcher::new(map.clone(), "./data", Duration::from_secs(2)).unwrap();
+/// // `map` is reloaded in background since now
+/// ```
+pub struct DirWatcher {
+    stop: Arc<AtomicBool>,
+    // removing the watch wakes the thread up to stop
+    watches: Watches,
+    wd: WatchDescriptor,
+    last_error: Arc<Mutex<Option<String>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DirWatcher {
+    pub fn new<T>(shared: Shared<T>, dir_path: impl Into<PathBuf>, debounce: Duration) -> io::Result<Self>
+        where T: AddEntity + Send + Sync + 'static {
+        Self::with_validator(shared, dir_path, debounce, Box::new(|_| Ok(())))
+    }
+
+    /// Like [DirWatcher::new], and the loaded map is published only if the validator returns `Ok`.
+    pub fn with_validator<T>(shared: Shared<T>, dir_path: impl Into<PathBuf>, debounce: Duration,
+                             validator: Validator<T>) -> io::Result<Self>
+        where T: AddEntity + Send + Sync + 'static {
+        let dir_path = dir_path.into();
+        let inotify = Inotify::init()?;
+        let watches = inotify.watches();
+        let wd = watches.clone().add(&dir_path, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO
+            | WatchMask::MOVED_FROM | WatchMask::DELETE)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let last_error = Arc::new(Mutex::new(None));
+        let state = WatchState {
+            inotify,
+            shared,
+            dir_path,
+            debounce,
+            validator,
+            stop: stop.clone(),
+            last_error: last_error.clone(),
+        };
+        let thread = std::thread::Builder::new()
+            .name("ip2c-watch".into())
+            .spawn(move || state.run())?;
+        Ok(DirWatcher { stop, watches, wd, last_error, thread: Some(thread) })
+    }
+
+    /// The error of the last reload, cleared by a successful one
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl Drop for DirWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // fails if the directory is gone, then the thread has stopped by itself
+        let _ = self.watches.remove(self.wd.clone());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct WatchState<T> {
+    inotify: Inotify,
+    shared: Shared<T>,
+    dir_path: PathBuf,
+    debounce: Duration,
+    validator: Validator<T>,
+    stop: Arc<AtomicBool>,
+    last_error: Arc<Mutex<Option<String>>>,
+}
+
+impl<T: AddEntity> WatchState<T> {
+    fn run(mut self) {
+        let mut buf = [0u8; 4096];
+        // the time to reload, reset by every event
+        let mut deadline: Option<Instant> = None;
+        loop {
+            let r = wait_events(&self.inotify, deadline).and_then(|_| {
+                if self.stop.load(Ordering::Relaxed) {
+                    return Ok(false);
+                }
+                match self.inotify.read_events(&mut buf) {
+                    Ok(events) => {
+                        for e in events {
+                            // the watch is removed, by the drop or with the directory
+                            if e.mask.contains(EventMask::IGNORED) {
+                                return Err(io::Error::new(io::ErrorKind::NotFound, "the directory is removed"));
+                            }
+                            if !e.mask.contains(EventMask::ISDIR) {
+                                deadline = Some(Instant::now() + self.debounce);
+                            }
+                        }
+                        Ok(true)
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(true),
+                    Err(e) => Err(e),
+                }
+            });
+            match r {
+                Ok(true) => {}
+                Ok(false) => return,
+                Err(_) if self.stop.load(Ordering::Relaxed) => return,
+                Err(e) => {
+                    self.set_error(Some(format!("watch {} failed: {}", self.dir_path.display(), e)));
+                    return;
+                }
+            }
+            if deadline.is_some_and(|t| Instant::now() >= t) {
+                deadline = None;
+                let r = self.reload().err().map(|e| e.to_string());
+                self.set_error(r);
+            }
+        }
+    }
+
+    fn reload(&self) -> Result<u64, Box<dyn Error>> {
+        let map = T::from_checked_dir(&self.dir_path)?;
+        (self.validator)(&map)?;
+        Ok(self.shared.replace(map))
+    }
+
+    fn set_error(&self, e: Option<String>) {
+        *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = e;
+    }
+}
+
+// blocks until there are events, or the deadline is reached
+fn wait_events(inotify: &Inotify, deadline: Option<Instant>) -> io::Result<()> {
+    // round up, so the deadline has passed after the timeout
+    let timeout = deadline.map_or(-1, |t| {
+        let ms = t.saturating_duration_since(Instant::now()).as_micros().div_ceil(1000);
+        ms.min(i32::MAX as u128) as i32
+    });
+    let mut fd = libc::pollfd { fd: inotify.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+    // SAFETY: `fd` is one valid pollfd, and the inotify outlives the call
+    if unsafe { libc::poll(&mut fd, 1, timeout) } < 0 {
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::{Duration, Instant};
+
+    use crate::rir::IpCodeMap;
+    use crate::rir::shared::SharedIpCodeMap;
+    use super::DirWatcher;
+
+    fn wait_for(f: impl Fn() -> bool) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            if f() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        false
+    }
+
+    #[test]
+    fn reload_on_change() {
+        let dir = std::env::temp_dir().join(format!("ip2c-watch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("tmp")).unwrap();
+
+        let shared = SharedIpCodeMap::new(IpCodeMap::new());
+        let watcher = DirWatcher::new(shared.clone(), &dir, Duration::from_millis(100)).unwrap();
+
+        // the header says 2 records, but only 1 is written
+        fs::write(dir.join("apnic.txt"), "2|apnic|20230313|2|19830613|20230310|+1000\n\
+            apnic|CN|ipv4|1.0.1.0|256|20110414|allocated\n").unwrap();
+        assert!(wait_for(|| watcher.last_error().is_some()));
+        assert_eq!(shared.generation(), 1);
+
+        // write in another place, then rename into place
+        fs::write(dir.join("tmp/apnic.txt"), "2|apnic|20230313|2|19830613|20230310|+1000\n\
+            apnic|*|ipv4|*|2|summary\n\
+            apnic|CN|ipv4|1.0.1.0|256|20110414|allocated\n\
+            apnic|JP|ipv4|1.0.16.0|4096|20110412|allocated\n").unwrap();
+        fs::rename(dir.join("tmp/apnic.txt"), dir.join("apnic.txt")).unwrap();
+        assert!(wait_for(|| shared.generation() == 2));
+        assert!(watcher.last_error().is_none());
+        assert_eq!(shared.query("1.0.16.1".parse().unwrap()).unwrap().name(), "JP");
+
+        // the version line is lost, but the last line is terminated
+        fs::write(dir.join("apnic.txt"), "apnic|CN|ipv4|1.0.1.0|256|20110414|allocated\n").unwrap();
+        assert!(wait_for(|| watcher.last_error().is_some_and(|e| e.contains("no version line"))));
+        assert_eq!(shared.generation(), 2);
+
+        drop(watcher);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
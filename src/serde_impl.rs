@@ -0,0 +1,245 @@
+//! `Serialize` and `Deserialize` of the public types, enabled by the `serde` feature.
+//!
+//! In human-readable formats (eg. JSON) the addresses are dotted or colon strings, and the intervals are
+//! CIDR strings if possible, otherwise `start-end` range strings. In binary formats they are integers.
+//! [IntervalTreeMap] is a sequence of interval-value pairs, and the non-overlap is validated on deserialize.
+
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{Error, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeSeq, SerializeStruct};
+
+use crate::{Cidr, Interval, IntervalTreeMap, IpTree, IPv4, IPv6};
+use crate::rir::CountryRegionCode;
+
+// parse the human-readable string with `FromStr`
+fn from_str<'de, D, T>(d: D) -> Result<T, D::Error>
+    where D: Deserializer<'de>, T: FromStr, T::Err: Display {
+    let s = <std::borrow::Cow<str>>::deserialize(d)?;
+    s.parse().map_err(D::Error::custom)
+}
+
+macro_rules! impl_serde_ip {
+    ($ip:ident, $bits:ty) => {
+        impl Serialize for $ip {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                if s.is_human_readable() {
+                    s.collect_str(self)
+                } else {
+                    self.0.serialize(s)
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ip {
+            fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                if d.is_human_readable() {
+                    from_str(d)
+                } else {
+                    <$bits>::deserialize(d).map($ip)
+                }
+            }
+        }
+
+        impl Serialize for Interval<$ip> {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                if s.is_human_readable() {
+                    match Cidr::try_from(*self) {
+                        Ok(c) => s.collect_str(&c),
+                        Err(_) => s.collect_str(&format_args!("{}-{}", self.0, self.1)),
+                    }
+                } else {
+                    (self.0, self.1).serialize(s)
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for Interval<$ip> {
+            fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                if d.is_human_readable() {
+                    from_str(d)
+                } else {
+                    let (a, b) = <($ip, $ip)>::deserialize(d)?;
+                    if a > b {
+                        return Err(D::Error::custom(format_args!("interval invalid: {}-{}", a, b)));
+                    }
+                    Ok(Interval(a, b))
+                }
+            }
+        }
+
+        impl Serialize for Cidr<$ip> {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                if s.is_human_readable() {
+                    s.collect_str(self)
+                } else {
+                    (self.network(), self.prefix_len()).serialize(s)
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for Cidr<$ip> {
+            fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                if d.is_human_readable() {
+                    from_str(d)
+                } else {
+                    let (a, n) = <($ip, u8)>::deserialize(d)?;
+                    Cidr::new(a, n).ok_or_else(|| D::Error::custom(format_args!("cidr invalid: {}/{}", a, n)))
+                }
+            }
+        }
+    };
+}
+
+impl_serde_ip!(IPv4, u32);
+impl_serde_ip!(IPv6, u128);
+
+impl Serialize for CountryRegionCode {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for CountryRegionCode {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<str>>::deserialize(d)?;
+        CountryRegionCode::new(&s).ok_or_else(|| D::Error::custom(format_args!("invalid code: {:?}", s)))
+    }
+}
+
+impl<K, V> Serialize for IntervalTreeMap<K, V>
+    where K: Ord + Copy, Interval<K>: Serialize, V: Serialize {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut seq = s.serialize_seq(Some(self.len()))?;
+        for pair in self.iter() {
+            seq.serialize_element(&pair)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de, K, V> Deserialize<'de> for IntervalTreeMap<K, V>
+    where K: Ord + Copy + Display, Interval<K>: Deserialize<'de>, V: Deserialize<'de> {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        d.deserialize_seq(TreeVisitor(PhantomData))
+    }
+}
+
+struct TreeVisitor<K, V>(PhantomData<(K, V)>);
+
+impl<'de, K, V> Visitor<'de> for TreeVisitor<K, V>
+    where K: Ord + Copy + Display, Interval<K>: Deserialize<'de>, V: Deserialize<'de> {
+    type Value = IntervalTreeMap<K, V>;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("a sequence of interval-value pairs")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut map = IntervalTreeMap::new();
+        while let Some((k, v)) = seq.next_element::<(Interval<K>, V)>()? {
+            map.insert(k, v).map_err(A::Error::custom)?;
+        }
+        Ok(map)
+    }
+}
+
+impl<T: Serialize> Serialize for IpTree<T> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut st = s.serialize_struct("IpTree", 2)?;
+        st.serialize_field("ipv4", &self.ipv4)?;
+        st.serialize_field("ipv6", &self.ipv6)?;
+        st.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for IpTree<T> {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        d.deserialize_struct("IpTree", &["ipv4", "ipv6"], IpTreeVisitor(PhantomData))
+    }
+}
+
+struct IpTreeVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for IpTreeVisitor<T> {
+    type Value = IpTree<T>;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("struct IpTree")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let ipv4 = seq.next_element()?.ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        let ipv6 = seq.next_element()?.ok_or_else(|| A::Error::invalid_length(1, &self))?;
+        Ok(IpTree { ipv4, ipv6 })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut ipv4 = None;
+        let mut ipv6 = None;
+        while let Some(key) = map.next_key::<std::borrow::Cow<str>>()? {
+            match key.as_ref() {
+                "ipv4" => ipv4 = Some(map.next_value()?),
+                "ipv6" => ipv6 = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(IpTree {
+            ipv4: ipv4.ok_or_else(|| A::Error::missing_field("ipv4"))?,
+            ipv6: ipv6.ok_or_else(|| A::Error::missing_field("ipv6"))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cidr, Interval, IpTree, IPv4, IPv6};
+    use crate::rir::{CountryRegionCode, IpCodeMap};
+
+    #[test]
+    fn human_readable() {
+        let v: Interval<IPv4> = "10.0.0.0/8".parse().unwrap();
+        assert_eq!(serde_json::to_string(&v).unwrap(), r#""10.0.0.0/8""#);
+        let v: Interval<IPv4> = "10.0.0.1-10.0.0.5".parse().unwrap();
+        assert_eq!(serde_json::to_string(&v).unwrap(), r#""10.0.0.1-10.0.0.5""#);
+        assert_eq!(serde_json::from_str::<Interval<IPv4>>(r#""10.0.0.1-5""#).unwrap(), v);
+        let ip: IPv6 = "2001:db8::1".parse().unwrap();
+        assert_eq!(serde_json::to_string(&ip).unwrap(), r#""2001:db8::1""#);
+        assert!(serde_json::from_str::<Cidr<IPv4>>(r#""10.0.0.1/8""#).is_err());
+
+        let mut map = IpCodeMap::new();
+        map.ipv4.insert("1.0.0.0/24".parse().unwrap(), CountryRegionCode::new("CN").unwrap()).unwrap();
+        map.ipv6.insert("2001:db8::/32".parse().unwrap(), CountryRegionCode::new("JP").unwrap()).unwrap();
+        let s = serde_json::to_string(&map).unwrap();
+        assert_eq!(s, r#"{"ipv4":[["1.0.0.0/24","CN"]],"ipv6":[["2001:db8::/32","JP"]]}"#);
+        let back: IpCodeMap = serde_json::from_str(&s).unwrap();
+        assert_eq!(back.ipv4.iter().collect::<Vec<_>>(), map.ipv4.iter().collect::<Vec<_>>());
+        assert_eq!(back.ipv6.len(), 1);
+    }
+
+    #[test]
+    fn overlap_rejected() {
+        let r = serde_json::from_str::<IpTree<u8>>(r#"{"ipv4":[["1.0.0.0/24",1],["1.0.0.128/25",2]],"ipv6":[]}"#);
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn binary() {
+        let mut map = IpTree::new();
+        map.ipv4.insert("1.0.0.0/24".parse().unwrap(), 1u8).unwrap();
+        map.ipv6.insert("2001:db8::/32".parse().unwrap(), 2u8).unwrap();
+        let bytes = bincode::serialize(&map).unwrap();
+        // length prefix + 2 * u32 + u8
+        assert_eq!(&bytes[..17], &[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 255, 0, 0, 1, 1]);
+        let back: IpTree<u8> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(back.ipv4.query("1.0.0.7".parse().unwrap()), Some(&1));
+        assert_eq!(back.ipv6.query("2001:db8::1".parse().unwrap()), Some(&2));
+        let cidr: Cidr<IPv4> = "10.0.0.0/8".parse().unwrap();
+        assert_eq!(bincode::deserialize::<Cidr<IPv4>>(&bincode::serialize(&cidr).unwrap()).unwrap(), cidr);
+    }
+}
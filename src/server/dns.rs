@@ -0,0 +1,473 @@
+//! The DNS TXT lookup responder in the style of Team Cymru, over UDP and TCP.
+//!
+//! The address is queried by the reversed octets (IPv4) or the reversed nibbles (IPv6) under the origin,
+//! eg. `1.1.0.1.origin.example.` for `1.0.1.1`, and answered by a TXT record of `prefix | cc | registry | date`,
+//! eg. `"1.0.1.0/24 | CN | apnic | 2011-04-14"`. A name not delegated is answered by NXDOMAIN, and a name which
+//! has the delegated addresses below, eg. `1.0.1.origin.example.`, by no data, both with the SOA of the origin.
+
+use std::io;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::{Cidr, Interval, IpAddress, IPv4, IPv6};
+use crate::rir::parse::{Delegated, Record, RecordMap};
+use crate::rir::shared::Shared;
+use crate::server::{format_date, serve_incoming, MAX_CONNECTIONS};
+
+/// the TTL of the answers
+pub const TTL: u32 = 3600;
+/// the TTL of the SOA, and so of the negative answers (RFC 2308)
+pub const NEGATIVE_TTL: u32 = 300;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+const TYPE_SOA: u16 = 6;
+const TYPE_TXT: u16 = 16;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+
+const RCODE_FORMERR: u8 = 1;
+const RCODE_NXDOMAIN: u8 = 3;
+const RCODE_NOTIMP: u8 = 4;
+const RCODE_REFUSED: u8 = 5;
+
+/// The query name of the address, eg. `4.3.2.1.origin.example.` for `1.2.3.4`.
+///
+/// ```
+/// use ip2c::server::dns::reverse_name;
+///
+/// assert_eq!(reverse_name("1.2.3.4".parse().unwrap(), "origin.example"), "4.3.2.1.origin.example.");
+/// assert!(reverse_name("2001:db8::1".parse().unwrap(), "origin6.example.")
+///     .starts_with("1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.origin6.example."));
+/// ```
+pub fn reverse_name(ip: IpAddr, origin: &str) -> String {
+    let mut s = String::new();
+    match ip {
+        IpAddr::V4(ip) => {
+            for o in ip.octets().iter().rev() {
+                s.push_str(&format!("{}.", o));
+            }
+        }
+        IpAddr::V6(ip) => {
+            for b in ip.octets().iter().rev() {
+                s.push_str(&format!("{:x}.{:x}.", b & 0xf, b >> 4));
+            }
+        }
+    }
+    s.push_str(origin.trim_end_matches('.'));
+    s.push('.');
+    s
+}
+
+// the address of the labels before the origin, `None` if they are not reversed octets or nibbles
+fn parse_reversed(labels: &[String]) -> Option<IpAddr> {
+    match labels.len() {
+        4 => Some(Ipv4Addr::from(<[u8; 4]>::try_from(octets(labels)?).ok()?).into()),
+        32 => Some(Ipv6Addr::from(nibbles(labels)?.iter().fold(0u128, |a, &n| a << 4 | n as u128)).into()),
+        _ => None,
+    }
+}
+
+// the octets of the reversed labels, eg. `[1, 0]` of `0.1`
+fn octets(labels: &[String]) -> Option<Vec<u8>> {
+    labels.iter().rev().map(|l| {
+        // no leading zeros
+        if l.len() > 1 && l.starts_with('0') {
+            return None;
+        }
+        l.parse().ok()
+    }).collect()
+}
+
+// the nibbles of the reversed labels, eg. `[2, 0]` of `0.2`
+fn nibbles(labels: &[String]) -> Option<Vec<u8>> {
+    labels.iter().rev().map(|l| if l.len() == 1 { u8::from_str_radix(l, 16).ok() } else { None }).collect()
+}
+
+// the names under the origin
+enum Node {
+    Txt(String),
+    // the origin, or an empty non-terminal which has the addresses below, eg. `1.0.1.origin.` of `1.0.1.0/24`
+    Empty,
+    Missing,
+}
+
+// the node of the labels before the origin
+fn resolve(map: &RecordMap, labels: &[String]) -> Node {
+    if labels.is_empty() {
+        return Node::Empty;
+    }
+    if let Some(txt) = parse_reversed(labels).and_then(|ip| lookup(map, ip)) {
+        return Node::Txt(txt);
+    }
+    let n = labels.len();
+    let ipv4 = (n < 4).then(|| octets(labels)).flatten().is_some_and(|o| {
+        let bits = o.iter().fold(0u32, |a, &b| a << 8 | b as u32) << (8 * (4 - n));
+        Cidr::new(IPv4::from(bits), 8 * n as u8).is_some_and(|c| map.ipv4.range(c.into()).next().is_some())
+    });
+    let ipv6 = (n < 32).then(|| nibbles(labels)).flatten().is_some_and(|o| {
+        let bits = o.iter().fold(0u128, |a, &b| a << 4 | b as u128) << (4 * (32 - n));
+        Cidr::new(IPv6::from(bits), 4 * n as u8).is_some_and(|c| map.ipv6.range(c.into()).next().is_some())
+    });
+    if ipv4 || ipv6 { Node::Empty } else { Node::Missing }
+}
+
+/// The DNS responder over the [Shared] delegated records, it listens on both UDP and TCP of the address.
+///
+/// ```
+/// use std::net::UdpSocket;
+/// use ip2c::rir::parse::Delegated;
+/// use ip2c::rir::shared::Shared;
+/// use ip2c::server::dns::DnsServer;
+///
+/// let map = Shared::<Delegated>::load_from_dir("./data").unwrap();
+/// let server = DnsServer::bind("127.0.0.1:0", map, "origin.example").unwrap();
+/// let addr = server.local_addr().unwrap();
+/// server.spawn();
+///
+/// // the query of TXT 1.0.0.10.origin.example.
+/// let mut query = vec![0x12, 0x34, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+/// for label in ["1", "0", "0", "10", "origin", "example"] {
+///     query.push(label.len() as u8);
+///     query.extend_from_slice(label.as_bytes());
+/// }
+/// query.extend_from_slice(&[0, 0, 16, 0, 1]);
+///
+/// let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+/// socket.send_to(&query, addr).unwrap();
+/// let mut buf = [0u8; 512];
+/// let n = socket.recv(&mut buf).unwrap();
+/// assert_eq!(&buf[..2], &[0x12, 0x34]);
+/// // no error, one answer
+/// assert_eq!(buf[3] & 0xf, 0);
+/// assert_eq!(&buf[6..8], &[0, 1]);
+/// assert!(buf[..n].ends_with(b"10.0.0.0/8 | -- |  | "));
+/// ```
+pub struct DnsServer {
+    udp: UdpSocket,
+    tcp: TcpListener,
+    state: Arc<State>,
+}
+
+struct State {
+    map: Shared<Delegated>,
+    // the lowercase labels
+    origin: Vec<String>,
+}
+
+impl DnsServer {
+    pub fn bind(addr: impl ToSocketAddrs, map: Shared<Delegated>, origin: &str) -> io::Result<Self> {
+        let udp = UdpSocket::bind(addr)?;
+        // the same port of UDP, useful if the port is 0
+        let tcp = TcpListener::bind(udp.local_addr()?)?;
+        let origin = origin.trim_end_matches('.').split('.')
+            .filter(|l| !l.is_empty())
+            .map(|l| l.to_ascii_lowercase())
+            .collect();
+        Ok(DnsServer { udp, tcp, state: Arc::new(State { map, origin }) })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.udp.local_addr()
+    }
+
+    /// Serves forever, UDP in a background thread and TCP one thread a connection, at most [MAX_CONNECTIONS] at a time.
+    pub fn run(self) -> io::Result<()> {
+        let DnsServer { udp, tcp, state } = self;
+        let udp_state = state.clone();
+        std::thread::spawn(move || serve_udp(&udp_state, udp));
+        serve_incoming(&tcp, MAX_CONNECTIONS, move |stream| {
+            let _ = serve_tcp(&state, stream);
+        })
+    }
+
+    /// Like [DnsServer::run], but in a background thread.
+    pub fn spawn(self) -> JoinHandle<io::Result<()>> {
+        std::thread::spawn(move || self.run())
+    }
+}
+
+fn serve_udp(state: &State, socket: UdpSocket) -> io::Result<()> {
+    let mut buf = [0u8; 512];
+    loop {
+        let (n, peer) = match socket.recv_from(&mut buf) {
+            Ok(r) => r,
+            // eg. ICMP port unreachable of the last response
+            Err(_) => continue,
+        };
+        if let Some(response) = answer(state, &buf[..n]) {
+            let _ = socket.send_to(&response, peer);
+        }
+    }
+}
+
+// the messages are prefixed by the 2 bytes length
+fn serve_tcp(state: &State, mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    loop {
+        let mut len = [0u8; 2];
+        match stream.read_exact(&mut len) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            r => r?,
+        }
+        let mut query = vec![0u8; u16::from_be_bytes(len) as usize];
+        stream.read_exact(&mut query)?;
+        let Some(response) = answer(state, &query) else {
+            return Ok(());
+        };
+        stream.write_all(&(response.len() as u16).to_be_bytes())?;
+        stream.write_all(&response)?;
+    }
+}
+
+struct Question {
+    labels: Vec<String>,
+    // the bytes of the question section, echoed in the response
+    raw_end: usize,
+    qtype: u16,
+    qclass: u16,
+}
+
+// `None` if the name is bad, the compression is not allowed in the question
+fn parse_question(msg: &[u8]) -> Option<Question> {
+    let mut labels = Vec::new();
+    let mut i = 12;
+    loop {
+        let len = *msg.get(i)? as usize;
+        i += 1;
+        if len == 0 {
+            break;
+        }
+        if len > 63 {
+            return None;
+        }
+        labels.push(String::from_utf8_lossy(msg.get(i..i + len)?).to_ascii_lowercase());
+        i += len;
+    }
+    let qtype = u16::from_be_bytes([*msg.get(i)?, *msg.get(i + 1)?]);
+    let qclass = u16::from_be_bytes([*msg.get(i + 2)?, *msg.get(i + 3)?]);
+    Some(Question { labels, raw_end: i + 4, qtype, qclass })
+}
+
+// `None` if the message is not a query, so no response
+fn answer(state: &State, msg: &[u8]) -> Option<Vec<u8>> {
+    if msg.len() < 12 || msg[2] & 0x80 != 0 {
+        return None;
+    }
+    let opcode = (msg[2] >> 3) & 0xf;
+    let qdcount = u16::from_be_bytes([msg[4], msg[5]]);
+    let question = if qdcount == 1 { parse_question(msg) } else { None };
+    let Some(question) = question else {
+        return Some(header(msg, RCODE_FORMERR, 0, 0));
+    };
+    let mut response = header(msg, 0, 1, 0);
+    response.extend_from_slice(&msg[12..question.raw_end]);
+    if opcode != 0 {
+        response[3] |= RCODE_NOTIMP;
+        return Some(response);
+    }
+    let labels = &question.labels;
+    if labels.len() < state.origin.len() || labels[labels.len() - state.origin.len()..] != state.origin[..] {
+        response[3] |= RCODE_REFUSED;
+        return Some(response);
+    }
+    let labels = &labels[..labels.len() - state.origin.len()];
+    let snapshot = state.map.snapshot();
+    let origin_at = skip_labels(msg, labels.len());
+    // the serial changes with the reloaded data
+    let serial = snapshot.generation as u32;
+    let is_type = |t: u16| question.qclass == CLASS_IN && (question.qtype == t || question.qtype == TYPE_ANY);
+    match resolve(&snapshot.map.records, labels) {
+        Node::Txt(txt) if is_type(TYPE_TXT) => {
+            response[7] = 1;
+            // the pointer to the name of the question
+            response.extend_from_slice(&[0xc0, 12]);
+            response.extend_from_slice(&TYPE_TXT.to_be_bytes());
+            response.extend_from_slice(&CLASS_IN.to_be_bytes());
+            response.extend_from_slice(&TTL.to_be_bytes());
+            let txt = &txt.as_bytes()[..txt.len().min(255)];
+            response.extend_from_slice(&(txt.len() as u16 + 1).to_be_bytes());
+            response.push(txt.len() as u8);
+            response.extend_from_slice(txt);
+        }
+        Node::Empty if labels.is_empty() && is_type(TYPE_SOA) => {
+            response[7] = 1;
+            push_soa(&mut response, origin_at, serial);
+        }
+        // the negative answers have the SOA in the authority section (RFC 2308)
+        node => {
+            if let Node::Missing = node {
+                response[3] |= RCODE_NXDOMAIN;
+            }
+            response[9] = 1;
+            push_soa(&mut response, origin_at, serial);
+        }
+    }
+    Some(response)
+}
+
+// the offset of the name after the first `n` labels of the question
+fn skip_labels(msg: &[u8], n: usize) -> usize {
+    (0..n).fold(12, |i, _| i + 1 + msg[i] as usize)
+}
+
+// the SOA of the origin at the offset of the message, the primary is the origin and the mailbox is `hostmaster`
+fn push_soa(response: &mut Vec<u8>, origin_at: usize, serial: u32) {
+    let origin = (0xc000 | origin_at as u16).to_be_bytes();
+    response.extend_from_slice(&origin);
+    response.extend_from_slice(&TYPE_SOA.to_be_bytes());
+    response.extend_from_slice(&CLASS_IN.to_be_bytes());
+    response.extend_from_slice(&NEGATIVE_TTL.to_be_bytes());
+    let mut rdata = origin.to_vec();
+    rdata.push(10);
+    rdata.extend_from_slice(b"hostmaster");
+    rdata.extend_from_slice(&origin);
+    // the serial, refresh, retry, expire and minimum
+    for n in [serial, TTL, 600, 86400, NEGATIVE_TTL] {
+        rdata.extend_from_slice(&n.to_be_bytes());
+    }
+    response.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    response.extend_from_slice(&rdata);
+}
+
+// the response header of the query
+fn header(query: &[u8], rcode: u8, qdcount: u16, ancount: u16) -> Vec<u8> {
+    let mut h = Vec::with_capacity(512);
+    h.extend_from_slice(&query[..2]);
+    // QR, the opcode and RD of the query, AA
+    h.push(0x80 | (query[2] & 0x79) | 0x04);
+    h.push(rcode);
+    h.extend_from_slice(&qdcount.to_be_bytes());
+    h.extend_from_slice(&ancount.to_be_bytes());
+    h.extend_from_slice(&[0, 0, 0, 0]);
+    h
+}
+
+// `prefix | cc | registry | date`
+fn lookup(map: &RecordMap, ip: IpAddr) -> Option<String> {
+    match ip {
+        IpAddr::V4(ip) => map.ipv4.get_key_value(ip.into()).map(|(k, r)| txt(prefix(*k, ip.into()), r)),
+        IpAddr::V6(ip) => map.ipv6.get_key_value(ip.into()).map(|(k, r)| txt(prefix(*k, ip.into()), r)),
+    }
+}
+
+// the range if it is a CIDR block, otherwise the block of the range containing the address
+fn prefix<T: IpAddress + std::fmt::Display>(range: Interval<T>, ip: T) -> String {
+    match Cidr::try_from(range) {
+        Ok(c) => c.to_string(),
+        Err(_) => range.cidrs().find(|c| c.contains(ip)).map(|c| c.to_string()).unwrap_or_default(),
+    }
+}
+
+fn txt(prefix: String, r: &Record) -> String {
+    format!("{} | {} | {} | {}", prefix, r.code, r.registry, format_date(&r.date))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{IpAddr, TcpStream};
+
+    use crate::rir::parse::{parse_line, Delegated};
+    use crate::rir::shared::Shared;
+    use super::{answer, reverse_name, DnsServer, State};
+
+    fn query(name: &str, qtype: u16) -> Vec<u8> {
+        let mut q = vec![0xab, 0xcd, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        for label in name.trim_end_matches('.').split('.') {
+            q.push(label.len() as u8);
+            q.extend_from_slice(label.as_bytes());
+        }
+        q.push(0);
+        q.extend_from_slice(&qtype.to_be_bytes());
+        q.extend_from_slice(&[0, 1]);
+        q
+    }
+
+    fn state() -> State {
+        let mut map = Delegated::default();
+        for line in ["apnic|CN|ipv4|1.0.1.0|256|20110414|allocated", "apnic|JP|ipv4|1.0.16.0|3000|20110412|allocated",
+            "apnic|CN|ipv6|2001:250::|35|20000426|allocated"] {
+            map.add_entity(parse_line(line).unwrap()).unwrap();
+        }
+        State { map: Shared::new(map), origin: vec!["origin".to_string(), "example".to_string()] }
+    }
+
+    // the rcode, and the number of the answers and the authorities
+    fn counts(r: &[u8]) -> (u8, u8, u8) {
+        (r[3] & 0xf, r[7], r[9])
+    }
+
+    // the rcode and the txt
+    fn ask(state: &State, ip: &str, qtype: u16) -> (u8, Option<String>) {
+        let q = query(&reverse_name(ip.parse::<IpAddr>().unwrap(), "Origin.Example"), qtype);
+        let r = answer(state, &q).unwrap();
+        assert_eq!(&r[..2], &q[..2]);
+        assert_eq!(&r[12..q.len()], &q[12..]);
+        let txt = if r[7] == 1 { Some(String::from_utf8_lossy(&r[q.len() + 13..]).into_owned()) } else { None };
+        (r[3] & 0xf, txt)
+    }
+
+    #[test]
+    fn answers() {
+        let state = state();
+        assert_eq!(ask(&state, "1.0.1.1", 16), (0, Some("1.0.1.0/24 | CN | apnic | 2011-04-14".to_string())));
+        // 1.0.16.0-1.0.27.183 is not a CIDR block
+        assert_eq!(ask(&state, "1.0.24.1", 16), (0, Some("1.0.24.0/23 | JP | apnic | 2011-04-12".to_string())));
+        assert_eq!(ask(&state, "2001:250::1", 255).1.unwrap(), "2001:250::/35 | CN | apnic | 2000-04-26");
+        assert_eq!(ask(&state, "1.0.1.1", 1), (0, None));
+        assert_eq!(ask(&state, "8.8.8.8", 16), (3, None));
+
+        assert_eq!(answer(&state, &query("1.0.1.1.other.example", 16)).unwrap()[3] & 0xf, 5);
+        assert_eq!(answer(&state, &query("x.1.0.1.origin.example", 16)).unwrap()[3] & 0xf, 3);
+        assert_eq!(answer(&state, &query("01.1.0.1.origin.example", 16)).unwrap()[3] & 0xf, 3);
+
+        // the SOA of the negative answers, after the question
+        let q = query("8.8.8.8.origin.example", 16);
+        let r = answer(&state, &q).unwrap();
+        assert_eq!(counts(&r), (3, 0, 1));
+        // the pointer to `origin.example` after the 4 labels, type SOA, class IN, TTL
+        assert_eq!(&r[q.len()..q.len() + 10], &[0xc0, 20, 0, 6, 0, 1, 0, 0, 1, 44]);
+        assert_eq!(&r[q.len() + 12..q.len() + 27], b"\xc0\x14\x0ahostmaster\xc0\x14");
+        // the serial is the generation
+        assert_eq!(&r[q.len() + 27..q.len() + 31], &[0, 0, 0, 1]);
+        assert_eq!(r.len(), q.len() + 47);
+        assert_eq!(counts(&answer(&state, &query("1.1.0.1.origin.example", 1)).unwrap()), (0, 0, 1));
+        // the empty non-terminals
+        for name in ["1.0.1.origin.example", "0.1.origin.example", "1.origin.example", "0.5.2.0.1.0.0.2.origin.example",
+            "origin.example"] {
+            assert_eq!(counts(&answer(&state, &query(name, 16)).unwrap()), (0, 0, 1), "{}", name);
+        }
+        for name in ["9.0.1.origin.example", "3.origin.example", "8.5.2.0.1.0.0.2.origin.example", "x.origin.example"] {
+            assert_eq!(counts(&answer(&state, &query(name, 16)).unwrap()), (3, 0, 1), "{}", name);
+        }
+        let q = query("origin.example", 6);
+        let r = answer(&state, &q).unwrap();
+        assert_eq!(counts(&r), (0, 1, 0));
+        assert_eq!(&r[q.len()..q.len() + 4], &[0xc0, 12, 0, 6]);
+        assert_eq!(answer(&state, &[0, 1, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 5, b'a']).unwrap()[3] & 0xf, 1);
+        // a response is not answered
+        assert!(answer(&state, &[0, 1, 0x81, 0, 0, 0, 0, 0, 0, 0, 0, 0]).is_none());
+    }
+
+    #[test]
+    fn tcp() {
+        let server = DnsServer::bind("127.0.0.1:0", state().map, "origin.example.").unwrap();
+        let addr = server.local_addr().unwrap();
+        server.spawn();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        for _ in 0..2 {
+            let q = query("1.1.0.1.origin.example", 16);
+            stream.write_all(&(q.len() as u16).to_be_bytes()).unwrap();
+            stream.write_all(&q).unwrap();
+            let mut len = [0u8; 2];
+            stream.read_exact(&mut len).unwrap();
+            let mut r = vec![0u8; u16::from_be_bytes(len) as usize];
+            stream.read_exact(&mut r).unwrap();
+            assert!(r.ends_with(b"1.0.1.0/24 | CN | apnic | 2011-04-14"));
+        }
+    }
+}
@@ -0,0 +1,438 @@
+//! The HTTP JSON lookup service.
+//!
+//! - `GET /v1/ip/{addr}`: `{"ip":"1.0.1.1","code":"CN"}`, `404` with `"code":null` if unmapped
+//! - `POST /v1/lookup`: a JSON array of addresses, eg. `["1.0.1.1","::1"]`, returns an array of the above
+//! - `GET /healthz`: `{"status":"ok","generation":1}`
+//! - `GET /metadata`: the generation, the number of ranges, and the dates and record counts of the data files
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::rir::parse::{summarize_file, Delegated};
+use crate::rir::shared::Shared;
+use crate::server::{serve_incoming, MAX_CONNECTIONS};
+use crate::util::json_str;
+
+const MAX_HEADER: usize = 8 * 1024;
+const MAX_BODY: usize = 1024 * 1024;
+/// the max number of addresses of one `POST /v1/lookup`
+pub const MAX_BATCH: usize = 10000;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The HTTP lookup server over the [Shared] delegated records, so they can be reloaded while serving.
+///
+/// ```
+/// use std::io::{Read, Write};
+/// use std::net::TcpStream;
+/// use ip2c::rir::parse::Delegated;
+/// use ip2c::rir::shared::Shared;
+/// use ip2c::server::http::HttpServer;
+///
+/// let map = Shared::<Delegated>::load_from_dir("./data").unwrap();
+/// let server = HttpServer::bind("127.0.0.1:0", map, Some("./data".into())).unwrap();
+/// let addr = server.local_addr().unwrap();
+/// server.spawn();
+///
+/// let mut stream = TcpStream::connect(addr).unwrap();
+/// stream.write_all(b"GET /v1/ip/10.0.0.1 HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+/// let mut response = String::new();
+/// stream.read_to_string(&mut response).unwrap();
+/// assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+/// assert!(response.ends_with(r#"{"ip":"10.0.0.1","code":"--"}"#));
+/// ```
+pub struct HttpServer {
+    listener: TcpListener,
+    state: Arc<State>,
+}
+
+struct State {
+    map: Shared<Delegated>,
+    data_dir: Option<PathBuf>,
+    // the metadata json of a generation, the data files are read once a generation
+    metadata: Mutex<Option<(u64, String)>>,
+}
+
+impl HttpServer {
+    /// `data_dir` is the directory the map loaded from, the data files in `/metadata` are read from it.
+    pub fn bind(addr: impl ToSocketAddrs, map: Shared<Delegated>, data_dir: Option<PathBuf>) -> io::Result<Self> {
+        Ok(HttpServer {
+            listener: TcpListener::bind(addr)?,
+            state: Arc::new(State { map, data_dir, metadata: Mutex::new(None) }),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves forever, one thread a connection, at most [MAX_CONNECTIONS] at a time.
+    pub fn run(&self) -> io::Result<()> {
+        let state = self.state.clone();
+        serve_incoming(&self.listener, MAX_CONNECTIONS, move |stream| {
+            let _ = serve(&state, stream);
+        })
+    }
+
+    /// Like [HttpServer::run], but in a background thread.
+    pub fn spawn(self) -> JoinHandle<io::Result<()>> {
+        std::thread::spawn(move || self.run())
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    body: String,
+}
+
+impl Response {
+    fn json(status: u16, body: String) -> Self {
+        Response { status, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Response { status, body: format!("{{\"error\":{}}}", json_str(message)) }
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+// one request a connection
+fn serve(state: &State, stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut r = BufReader::new(stream.try_clone()?);
+    let response = match read_request(&mut r)? {
+        Ok(req) => handle(state, &req),
+        Err(e) => e,
+    };
+    let mut w = stream;
+    write!(w, "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+           response.status, reason(response.status), response.body.len())?;
+    w.write_all(response.body.as_bytes())?;
+    w.flush()
+}
+
+fn read_request(r: &mut impl BufRead) -> io::Result<Result<Request, Response>> {
+    let mut head = Vec::new();
+    // the header ends with an empty line
+    loop {
+        let n = r.by_ref().take((MAX_HEADER - head.len()) as u64 + 1).read_until(b'\n', &mut head)?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        if head.len() > MAX_HEADER {
+            return Ok(Err(Response::error(431, "header too large")));
+        }
+        if head.ends_with(b"\n\r\n") || head.ends_with(b"\n\n") {
+            break;
+        }
+    }
+    let head = String::from_utf8_lossy(&head);
+    let mut lines = head.lines();
+    let mut parts = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(Err(Response::error(400, "bad request line")));
+    };
+    let mut length = None;
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                match value.trim().parse::<usize>() {
+                    Ok(n) => length = Some(n),
+                    Err(_) => return Ok(Err(Response::error(400, "bad content-length"))),
+                }
+            }
+        }
+    }
+    let mut body = Vec::new();
+    match length {
+        Some(n) if n > MAX_BODY => return Ok(Err(Response::error(413, "body too large"))),
+        Some(n) => {
+            body.resize(n, 0);
+            r.read_exact(&mut body)?;
+        }
+        None if method == "POST" => return Ok(Err(Response::error(411, "content-length required"))),
+        None => {}
+    }
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    Ok(Ok(Request { method: method.to_string(), path: percent_decode(path), body }))
+}
+
+fn percent_decode(s: &str) -> String {
+    let b = s.as_bytes();
+    let mut v = Vec::with_capacity(b.len());
+    let mut i = 0;
+    while i < b.len() {
+        let hex = b.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (b[i], hex) {
+            (b'%', Some(c)) => {
+                v.push(c);
+                i += 3;
+            }
+            (c, _) => {
+                v.push(c);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&v).into_owned()
+}
+
+fn handle(state: &State, req: &Request) -> Response {
+    let method = req.method.as_str();
+    match req.path.as_str() {
+        "/healthz" if method == "GET" => {
+            Response::json(200, format!("{{\"status\":\"ok\",\"generation\":{}}}", state.map.generation()))
+        }
+        "/metadata" if method == "GET" => Response::json(200, metadata(state)),
+        "/v1/lookup" if method == "POST" => lookup_batch(state, &req.body),
+        p if method == "GET" && p.starts_with("/v1/ip/") => {
+            let addr = &p["/v1/ip/".len()..];
+            match lookup(state, addr) {
+                (true, body) => Response::json(200, body),
+                (false, body) if addr.parse::<IpAddr>().is_ok() => Response::json(404, body),
+                (false, body) => Response::json(400, body),
+            }
+        }
+        "/healthz" | "/metadata" | "/v1/lookup" => Response::error(405, "method not allowed"),
+        p if p.starts_with("/v1/ip/") => Response::error(405, "method not allowed"),
+        _ => Response::error(404, "not found"),
+    }
+}
+
+// returns whether it is mapped, and the json
+fn lookup(state: &State, addr: &str) -> (bool, String) {
+    let Ok(ip) = addr.parse::<IpAddr>() else {
+        return (false, format!("{{\"ip\":{},\"error\":\"invalid address\"}}", json_str(addr)));
+    };
+    match state.map.snapshot().map.records.query(ip) {
+        Some(r) => (true, format!("{{\"ip\":\"{}\",\"code\":{}}}", ip, json_str(r.code.name()))),
+        None => (false, format!("{{\"ip\":\"{}\",\"code\":null}}", ip)),
+    }
+}
+
+fn lookup_batch(state: &State, body: &[u8]) -> Response {
+    let addrs = match std::str::from_utf8(body).map_err(|e| e.to_string()).and_then(parse_string_array) {
+        Ok(v) => v,
+        Err(e) => return Response::error(400, &format!("expect a json array of addresses: {}", e)),
+    };
+    if addrs.len() > MAX_BATCH {
+        return Response::error(413, &format!("too many addresses, the max is {}", MAX_BATCH));
+    }
+    let mut s = String::from("[");
+    for (i, addr) in addrs.iter().enumerate() {
+        if i > 0 {
+            s.push(',');
+        }
+        s.push_str(&lookup(state, addr).1);
+    }
+    s.push(']');
+    Response::json(200, s)
+}
+
+// a json array of strings, eg. `["1.0.1.1", "::1"]`
+fn parse_string_array(s: &str) -> Result<Vec<String>, String> {
+    let mut chars = s.trim().chars().peekable();
+    let mut v = Vec::new();
+    let skip_ws = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    };
+    if chars.next() != Some('[') {
+        return Err("expect '['".to_string());
+    }
+    skip_ws(&mut chars);
+    if chars.next_if_eq(&']').is_none() {
+        loop {
+            skip_ws(&mut chars);
+            if chars.next() != Some('"') {
+                return Err("expect a string".to_string());
+            }
+            let mut item = String::new();
+            loop {
+                match chars.next().ok_or("unterminated string")? {
+                    '"' => break,
+                    '\\' => match chars.next().ok_or("unterminated string")? {
+                        'u' => item.push(parse_unicode_escape(&mut chars)?),
+                        'n' => item.push('\n'),
+                        't' => item.push('\t'),
+                        'r' => item.push('\r'),
+                        'b' => item.push('\u{8}'),
+                        'f' => item.push('\u{c}'),
+                        c @ ('"' | '\\' | '/') => item.push(c),
+                        c => return Err(format!("bad escape '\\{}'", c)),
+                    },
+                    c => item.push(c),
+                }
+            }
+            v.push(item);
+            skip_ws(&mut chars);
+            match chars.next() {
+                Some(',') => continue,
+                Some(']') => break,
+                _ => return Err("expect ',' or ']'".to_string()),
+            }
+        }
+    }
+    if chars.next().is_some() {
+        return Err("trailing characters".to_string());
+    }
+    Ok(v)
+}
+
+// the code point after `\u`, a surrogate pair is joined, eg. `\uD83D\uDE00`
+fn parse_unicode_escape(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<char, String> {
+    let high = parse_hex4(chars)?;
+    let unpaired = || format!("unpaired surrogate '\\u{:04X}'", high);
+    let c = match high {
+        0xd800..=0xdbff => {
+            if chars.next() != Some('\\') || chars.next() != Some('u') {
+                return Err(unpaired());
+            }
+            let low = parse_hex4(chars)?;
+            if !(0xdc00..=0xdfff).contains(&low) {
+                return Err(unpaired());
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        }
+        0xdc00..=0xdfff => return Err(unpaired()),
+        c => c,
+    };
+    char::from_u32(c).ok_or_else(unpaired)
+}
+
+fn parse_hex4(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<u32, String> {
+    let hex: String = chars.by_ref().take(4).collect();
+    if hex.len() != 4 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("bad unicode escape '\\u{}'", hex));
+    }
+    u32::from_str_radix(&hex, 16).map_err(|e| e.to_string())
+}
+
+fn metadata(state: &State) -> String {
+    let snapshot = state.map.snapshot();
+    let mut cache = state.metadata.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((generation, json)) = cache.as_ref() {
+        if *generation == snapshot.generation {
+            return json.clone();
+        }
+    }
+    let loaded_at = snapshot.loaded_at.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let mut s = format!("{{\"generation\":{},\"loaded_at\":{},\"ipv4_ranges\":{},\"ipv6_ranges\":{},\"files\":[",
+                        snapshot.generation, loaded_at, snapshot.map.records.ipv4.len(), snapshot.map.records.ipv6.len());
+    let mut files: Vec<PathBuf> = state.data_dir.iter()
+        .flat_map(|dir| fs::read_dir(dir).into_iter().flatten())
+        .flatten()
+        .filter(|f| f.file_type().is_ok_and(|t| !t.is_dir()))
+        .map(|f| f.path())
+        .collect();
+    files.sort();
+    let mut first = true;
+    for path in files {
+        let Ok(summary) = summarize_file(&path) else {
+            continue;
+        };
+        if !first {
+            s.push(',');
+        }
+        first = false;
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        let _ = write!(s, "{{\"name\":{},\"registry\":{},\"serial\":{},\"start_date\":{},\"end_date\":{},\"records\":{}}}",
+                       json_str(&name), json_str(&summary.registry), json_str(&summary.serial),
+                       json_str(&summary.start_date), json_str(&summary.end_date), summary.records);
+    }
+    s.push_str("]}");
+    *cache = Some((snapshot.generation, s.clone()));
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+
+    use crate::rir::parse::Delegated;
+    use crate::rir::shared::Shared;
+    use super::{parse_string_array, percent_decode, HttpServer};
+
+    fn request(addr: SocketAddr, req: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(req.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split_once("\r\n\r\n").unwrap().1.to_string();
+        (status, body)
+    }
+
+    fn post(addr: SocketAddr, path: &str, body: &str) -> (u16, String) {
+        request(addr, &format!("POST {} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", path, body.len(), body))
+    }
+
+    #[test]
+    fn endpoints() {
+        let map = Shared::<Delegated>::load_from_dir("./data").unwrap();
+        let server = HttpServer::bind("127.0.0.1:0", map.clone(), Some("./data".into())).unwrap();
+        let addr = server.local_addr().unwrap();
+        server.spawn();
+
+        assert_eq!(request(addr, "GET /v1/ip/127.0.0.1 HTTP/1.1\r\n\r\n"), (200, r#"{"ip":"127.0.0.1","code":"--"}"#.to_string()));
+        assert_eq!(request(addr, "GET /v1/ip/8.8.8.8?x=1 HTTP/1.1\r\n\r\n"), (404, r#"{"ip":"8.8.8.8","code":null}"#.to_string()));
+        // `::/0` is in example.txt
+        assert_eq!(request(addr, "GET /v1/ip/%3A%3A1 HTTP/1.0\r\n\r\n"), (200, r#"{"ip":"::1","code":"--"}"#.to_string()));
+        assert_eq!(request(addr, "GET /v1/ip/bad HTTP/1.1\r\n\r\n").0, 400);
+        assert_eq!(request(addr, "DELETE /v1/ip/1.1.1.1 HTTP/1.1\r\n\r\n").0, 405);
+        assert_eq!(request(addr, "GET /nothing HTTP/1.1\r\n\r\n").0, 404);
+        assert_eq!(request(addr, "POST /v1/lookup HTTP/1.1\r\n\r\n").0, 411);
+
+        assert_eq!(post(addr, "/v1/lookup", r#" ["10.0.0.1", "8.8.8.8","x"] "#), (200,
+            r#"[{"ip":"10.0.0.1","code":"--"},{"ip":"8.8.8.8","code":null},{"ip":"x","error":"invalid address"}]"#.to_string()));
+        assert_eq!(post(addr, "/v1/lookup", "[]"), (200, "[]".to_string()));
+        assert_eq!(post(addr, "/v1/lookup", "1.1.1.1").0, 400);
+
+        assert_eq!(request(addr, "GET /healthz HTTP/1.1\r\n\r\n"), (200, r#"{"status":"ok","generation":1}"#.to_string()));
+        let (status, body) = request(addr, "GET /metadata HTTP/1.1\r\n\r\n");
+        assert_eq!(status, 200);
+        assert!(body.starts_with(r#"{"generation":1,"#));
+        assert!(body.ends_with(r#""files":[{"name":"example.txt","registry":"","serial":"","start_date":"","end_date":"","records":3}]}"#));
+        map.reload_from_dir("./data").unwrap();
+        assert!(request(addr, "GET /metadata HTTP/1.1\r\n\r\n").1.starts_with(r#"{"generation":2,"#));
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_string_array(r#"[ "a" , "b\"A" ]"#).unwrap(), ["a", "b\"A"]);
+        assert!(parse_string_array(r#"["a",]"#).is_err());
+        assert!(parse_string_array(r#"["a"] x"#).is_err());
+        assert!(parse_string_array(r#"["a"#).is_err());
+        assert_eq!(parse_string_array(r#"["\u0031.1\uD83D\uDE00", "\u00e9"]"#).unwrap(), ["1.1\u{1F600}", "é"]);
+        assert_eq!(parse_string_array(r#"["\uD83D"]"#).unwrap_err(), "unpaired surrogate '\\uD83D'");
+        assert_eq!(parse_string_array(r#"["\uD83Dx"]"#).unwrap_err(), "unpaired surrogate '\\uD83D'");
+        assert_eq!(parse_string_array(r#"["\uDE00\uD83D"]"#).unwrap_err(), "unpaired surrogate '\\uDE00'");
+        assert_eq!(parse_string_array(r#"["\u12"]"#).unwrap_err(), "bad unicode escape '\\u12\"]'");
+        assert_eq!(percent_decode("/v1/ip/2001%3adb8::1%"), "/v1/ip/2001:db8::1%");
+    }
+}
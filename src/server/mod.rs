@@ -0,0 +1,91 @@
+//! Lookup services for the tools which can't link Rust directly, enabled by the `server` feature.
+//!
+//! The services are plain blocking servers on std sockets, one thread a connection,
+//! at most [MAX_CONNECTIONS] connections of a service at a time.
+
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+pub mod http;
+pub mod dns;
+pub mod whois;
+
+/// The connections served at the same time by each TCP service, the others wait in the listen backlog.
+pub const MAX_CONNECTIONS: usize = 256;
+
+// the wait after a failed accept, eg. out of file descriptors, instead of spinning
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+
+// accepts forever, serves each connection in a thread, at most `max` at once
+fn serve_incoming(listener: &TcpListener, max: usize, serve: impl Fn(TcpStream) + Send + Sync + 'static) -> io::Result<()> {
+    let serve = Arc::new(serve);
+    let active = Arc::new((Mutex::new(0usize), Condvar::new()));
+    loop {
+        {
+            let (n, cond) = &*active;
+            let mut n = cond.wait_while(n.lock().unwrap_or_else(|e| e.into_inner()), |n| *n >= max).unwrap_or_else(|e| e.into_inner());
+            *n += 1;
+        }
+        let stream = loop {
+            match listener.accept() {
+                Ok((stream, _)) => break stream,
+                Err(_) => std::thread::sleep(ACCEPT_RETRY),
+            }
+        };
+        let serve = serve.clone();
+        let active = active.clone();
+        std::thread::spawn(move || {
+            let _slot = Slot(active);
+            serve(stream);
+        });
+    }
+}
+
+// frees the connection slot when the serving thread ends, even by a panic
+struct Slot(Arc<(Mutex<usize>, Condvar)>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let (n, cond) = &*self.0;
+        *n.lock().unwrap_or_else(|e| e.into_inner()) -= 1;
+        cond.notify_one();
+    }
+}
+
+// yyyymmdd of the delegated files to yyyy-mm-dd
+fn format_date(date: &str) -> String {
+    match date.as_bytes() {
+        d if d.len() == 8 && d.iter().all(u8::is_ascii_digit) => format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..]),
+        _ => date.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use crate::server::serve_incoming;
+
+    #[test]
+    fn connection_cap() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || serve_incoming(&listener, 1, move |mut stream| {
+            tx.send(()).unwrap();
+            // until the client closes
+            let _ = stream.read(&mut [0; 1]);
+        }));
+        let first = TcpStream::connect(addr).unwrap();
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        let _second = TcpStream::connect(addr).unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+        drop(first);
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+}
@@ -0,0 +1,248 @@
+//! The whois (RFC 3912) service of the delegated records.
+//!
+//! The query is an address (`1.0.1.1`), a range (`1.0.0.0/16`, `1.0.1.0 - 1.0.3.255`, `2001:250::/32`)
+//! or an autonomous system number (`AS4134` or `4134`), after the ignored client flags like `-r` or `-V client`,
+//! the response is the records in the RPSL-like `attribute: value` blocks, eg.
+//!
+//! ```text
+//! inetnum:        1.0.1.0 - 1.0.1.255
+//! cidr:           1.0.1.0/24
+//! registry:       apnic
+//! country:        CN
+//! status:         allocated
+//! date:           2011-04-14
+//! opaque-id:      A92E1062
+//! ```
+
+use std::fmt::{Display, Write as _};
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::{Interval, IntervalTreeMap, IpAddress, IPv4, IPv6};
+use crate::rir::parse::{Delegated, Record};
+use crate::rir::shared::Shared;
+use crate::server::{format_date, serve_incoming, MAX_CONNECTIONS};
+
+/// the max number of records of one response
+pub const MAX_RECORDS: usize = 256;
+const MAX_QUERY: usize = 1024;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// the flags of the RIPE-like clients, eg. `whois -r -B 1.0.1.1`, which are ignored
+const FLAGS: &[&str] = &["-r", "-B", "-G", "-a", "-b", "-c", "-x", "-l", "-L", "-m", "-M", "-K"];
+const FLAGS_WITH_VALUE: &[&str] = &["-T", "-i", "-s", "-t", "-v", "-q", "-V"];
+
+const BANNER: &str = "% ip2c whois server, the delegated records of the regional internet registries\n\n";
+
+impl Delegated {
+    /// The whois response of the query, the blank spaces around are ignored, and so are the ones around `-` of a range.
+    ///
+    /// ```
+    /// use ip2c::rir::parse::Delegated;
+    ///
+    /// let mut db = Delegated::default();
+    /// db.load_from_dir("./data").unwrap();
+    /// let response = db.whois("10.1.2.3");
+    /// assert!(response.contains("inetnum:        10.0.0.0 - 10.255.255.255\ncidr:           10.0.0.0/8\n"));
+    /// assert!(db.whois("AS4134").ends_with("% no entries found\n"));
+    /// ```
+    pub fn whois(&self, query: &str) -> String {
+        let query = query.trim();
+        let mut s = String::from(BANNER);
+        // the range as written in the response, eg. `1.0.1.0 - 1.0.1.255`
+        let range: String = query.split_whitespace().collect();
+        let n = if let Some(asn) = parse_asn(query) {
+            write_blocks(&mut s, self.asn.range(Interval(asn, asn)), "aut-num", |k| {
+                if k.0 == k.1 { format!("AS{}", k.0) } else { format!("AS{} - AS{}", k.0, k.1) }
+            })
+        } else if let Ok(span) = range.parse::<Interval<IPv4>>() {
+            write_ip_blocks(&mut s, &self.records.ipv4, span, "inetnum")
+        } else if let Ok(span) = range.parse::<Interval<IPv6>>() {
+            write_ip_blocks(&mut s, &self.records.ipv6, span, "inet6num")
+        } else {
+            let _ = writeln!(s, "% invalid query: {:?}", query);
+            return s;
+        };
+        if n == 0 {
+            s.push_str("% no entries found\n");
+        }
+        s
+    }
+}
+
+// the query after the known client flags, eg. `1.0.1.0 - 1.0.1.255` of `-r -V client 1.0.1.0 - 1.0.1.255`
+fn strip_flags(line: &str) -> &str {
+    let mut rest = line.trim_start();
+    loop {
+        let (word, after) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        if FLAGS.contains(&word) {
+            rest = after.trim_start();
+        } else if FLAGS_WITH_VALUE.contains(&word) {
+            let after = after.trim_start();
+            rest = after.split_once(char::is_whitespace).map_or("", |(_, r)| r.trim_start());
+        } else {
+            return rest;
+        }
+    }
+}
+
+// `AS4134`, `as4134` or `4134`
+fn parse_asn(s: &str) -> Option<u32> {
+    let digits = s.strip_prefix("AS").or_else(|| s.strip_prefix("as")).unwrap_or(s);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+fn write_ip_blocks<K>(s: &mut String, tree: &IntervalTreeMap<K, Record>, span: Interval<K>, name: &str) -> usize
+    where K: IpAddress + Display {
+    write_blocks(s, tree.range(span), name, |k| {
+        let mut v = format!("{} - {}", k.0, k.1);
+        for c in k.cidrs() {
+            let _ = write!(v, "\n{:<16}{}", "cidr:", c);
+        }
+        v
+    })
+}
+
+// returns the number of the records
+fn write_blocks<'a, K: Ord + Copy + 'a>(s: &mut String, records: impl Iterator<Item=(&'a Interval<K>, &'a Record)>,
+                                         name: &str, key: impl Fn(&Interval<K>) -> String) -> usize {
+    let mut n = 0;
+    for (k, r) in records {
+        if n == MAX_RECORDS {
+            let _ = writeln!(s, "% truncated, only the first {} records are shown\n", MAX_RECORDS);
+            break;
+        }
+        n += 1;
+        let _ = writeln!(s, "{:<16}{}", format!("{}:", name), key(k));
+        let _ = writeln!(s, "{:<16}{}", "registry:", r.registry);
+        let _ = writeln!(s, "{:<16}{}", "country:", r.code);
+        let _ = writeln!(s, "{:<16}{}", "status:", r.state);
+        let _ = writeln!(s, "{:<16}{}", "date:", format_date(&r.date));
+        if let Some(id) = &r.opaque_id {
+            let _ = writeln!(s, "{:<16}{}", "opaque-id:", id);
+        }
+        s.push('\n');
+    }
+    n
+}
+
+/// The whois server over the [Shared] delegated records, one query a connection, eg. `whois -h localhost -p 4343 1.0.1.1`.
+///
+/// ```
+/// use std::io::{Read, Write};
+/// use std::net::TcpStream;
+/// use ip2c::rir::parse::Delegated;
+/// use ip2c::rir::shared::Shared;
+/// use ip2c::server::whois::WhoisServer;
+///
+/// let db = Shared::<Delegated>::load_from_dir("./data").unwrap();
+/// let server = WhoisServer::bind("127.0.0.1:0", db).unwrap();
+/// let addr = server.local_addr().unwrap();
+/// server.spawn();
+///
+/// let mut stream = TcpStream::connect(addr).unwrap();
+/// stream.write_all(b"127.0.0.1\r\n").unwrap();
+/// let mut response = String::new();
+/// stream.read_to_string(&mut response).unwrap();
+/// assert!(response.contains("\r\ncidr:           127.0.0.0/8\r\n"));
+/// ```
+pub struct WhoisServer {
+    listener: TcpListener,
+    db: Shared<Delegated>,
+}
+
+impl WhoisServer {
+    pub fn bind(addr: impl ToSocketAddrs, db: Shared<Delegated>) -> io::Result<Self> {
+        Ok(WhoisServer { listener: TcpListener::bind(addr)?, db })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves forever, one thread a connection, at most [MAX_CONNECTIONS] at a time.
+    pub fn run(&self) -> io::Result<()> {
+        let db = self.db.clone();
+        serve_incoming(&self.listener, MAX_CONNECTIONS, move |stream| {
+            let _ = serve(&db, stream);
+        })
+    }
+
+    /// Like [WhoisServer::run], but in a background thread.
+    pub fn spawn(self) -> JoinHandle<io::Result<()>> {
+        std::thread::spawn(move || self.run())
+    }
+}
+
+fn serve(db: &Shared<Delegated>, stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut line = Vec::new();
+    BufReader::new(stream.try_clone()?).take(MAX_QUERY as u64).read_until(b'\n', &mut line)?;
+    let line = String::from_utf8_lossy(&line);
+    let query = strip_flags(&line);
+    let mut w = stream;
+    // RFC 3912 lines end with CRLF
+    w.write_all(db.snapshot().map.whois(query).replace('\n', "\r\n").as_bytes())?;
+    w.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rir::parse::{parse_line, Delegated};
+    use super::strip_flags;
+
+    fn db() -> Delegated {
+        let mut db = Delegated::default();
+        for line in ["apnic|CN|ipv4|1.0.1.0|256|20110414|allocated|A92E1062",
+            "apnic|CN|ipv4|1.0.2.0|512|20110414|allocated|A92E1062",
+            "apnic|CN|ipv6|2001:250::|35|20000426|allocated|A9160B4B",
+            "apnic|CN|asn|4134|2|20021210|allocated|A92E1062",
+            "ripencc|NL|ipv4|2.56.8.0|1024|20190212|allocated"] {
+            db.add_entity(parse_line(line).unwrap()).unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn queries() {
+        let db = db();
+        let r = db.whois("1.0.1.1");
+        assert!(r.ends_with("\
+inetnum:        1.0.1.0 - 1.0.1.255
+cidr:           1.0.1.0/24
+registry:       apnic
+country:        CN
+status:         allocated
+date:           2011-04-14
+opaque-id:      A92E1062
+
+"));
+        assert_eq!(db.whois("1.0.0.0/16").matches("inetnum:").count(), 2);
+        assert_eq!(db.whois("1.0.1.128-1.0.2.1").matches("inetnum:").count(), 2);
+        assert!(db.whois("2.56.8.0/22").contains("country:        NL\nstatus:         allocated\ndate:           2019-02-12\n\n"));
+        assert!(db.whois(" 2001:250:1::/48\r\n").contains("inet6num:       2001:250:: - 2001:250:1fff:ffff:ffff:ffff:ffff:ffff\ncidr:           2001:250::/35\n"));
+        assert!(db.whois("as4135").contains("aut-num:        AS4134 - AS4135\n"));
+        assert!(db.whois("4134").contains("opaque-id:      A92E1062\n"));
+        assert!(db.whois("8.8.8.8").ends_with("% no entries found\n"));
+        assert!(db.whois("AS").contains("% invalid query: \"AS\"\n"));
+        assert!(db.whois("example.com").contains("% invalid query"));
+        assert_eq!(db.whois("1.0.1.0 - 1.0.2.255").matches("inetnum:").count(), 2);
+    }
+
+    #[test]
+    fn flags() {
+        assert_eq!(strip_flags("-r -B 1.0.1.0 - 1.0.1.255\r\n"), "1.0.1.0 - 1.0.1.255\r\n");
+        assert_eq!(strip_flags(" -V  client-1.0 -T inetnum AS4134"), "AS4134");
+        assert_eq!(strip_flags("1.0.1.0 - 1.0.1.255"), "1.0.1.0 - 1.0.1.255");
+        assert_eq!(strip_flags("-z 1.0.1.1"), "-z 1.0.1.1");
+        assert_eq!(strip_flags("-r"), "");
+        assert_eq!(strip_flags("-T"), "");
+    }
+}
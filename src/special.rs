@@ -0,0 +1,255 @@
+//! Compiled-in IANA special-purpose address registries ([RFC 6890](https://www.rfc-editor.org/rfc/rfc6890)).
+//!
+//! Private, loopback, link-local, documentation, CGNAT, multicast, benchmarking and other
+//! reserved blocks never appear in RIR delegated files with a real country code.
+//! Use [special_purpose] to get the registry as an [IpTree], or [IpCodeMap::query_special_first]
+//! to consult it before the RIR map.
+//!
+//! ```
+//! use ip2c::special;
+//!
+//! let sp = special::query("192.168.1.1".parse().unwrap()).unwrap();
+//! assert_eq!(sp.name, "Private-Use");
+//! assert!(!sp.is_globally_reachable());
+//! assert!(special::query("8.8.8.8".parse().unwrap()).is_none());
+//! ```
+
+use std::cmp::Reverse;
+use std::fmt::{Debug, Display, Formatter};
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::{Discrete, Interval, IntervalTreeMap, IpTree};
+use crate::rir::{CountryRegionCode, IpCodeMap};
+
+/// One entry of the IANA IPv4/IPv6 special-purpose address registry
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SpecialPurpose {
+    /// the registered address block, eg. `10.0.0.0/8`
+    pub block: &'static str,
+    pub name: &'static str,
+    /// the RFC(s) which define the block
+    pub rfc: &'static str,
+    /// valid as source address
+    pub source: bool,
+    /// valid as destination address
+    pub destination: bool,
+    /// can be forwarded by routers
+    pub forwardable: bool,
+    /// `None` means the registry declares it `N/A`
+    pub globally_reachable: Option<bool>,
+    pub reserved_by_protocol: bool,
+}
+
+impl SpecialPurpose {
+    /// `N/A` in the registry is treated as not reachable
+    pub fn is_globally_reachable(&self) -> bool {
+        self.globally_reachable.unwrap_or(false)
+    }
+}
+
+impl Display for SpecialPurpose {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{} ({}, {})", self.name, self.block, self.rfc))
+    }
+}
+
+/// The result of [IpCodeMap::query_special_first]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Lookup {
+    Special(&'static SpecialPurpose),
+    Code(CountryRegionCode),
+}
+
+impl Display for Lookup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Lookup::Special(sp) => f.write_str(sp.name),
+            Lookup::Code(code) => Display::fmt(code, f),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+const fn sp(block: &'static str, name: &'static str, rfc: &'static str, source: bool, destination: bool,
+            forwardable: bool, globally_reachable: Option<bool>, reserved_by_protocol: bool) -> SpecialPurpose {
+    SpecialPurpose { block, name, rfc, source, destination, forwardable, globally_reachable, reserved_by_protocol }
+}
+
+const T: Option<bool> = Some(true);
+const F: Option<bool> = Some(false);
+
+// https://www.iana.org/assignments/iana-ipv4-special-registry/
+// 224.0.0.0/4 multicast is not part of the registry, but is listed here as well
+const IPV4_SPECIAL: &[SpecialPurpose] = &[
+    sp("0.0.0.0/8", "This network", "RFC791", true, false, false, F, true),
+    sp("0.0.0.0/32", "This host on this network", "RFC1122", true, false, false, F, true),
+    sp("10.0.0.0/8", "Private-Use", "RFC1918", true, true, true, F, false),
+    sp("100.64.0.0/10", "Shared Address Space", "RFC6598", true, true, true, F, false),
+    sp("127.0.0.0/8", "Loopback", "RFC1122", false, false, false, F, true),
+    sp("169.254.0.0/16", "Link Local", "RFC3927", true, true, false, F, true),
+    sp("172.16.0.0/12", "Private-Use", "RFC1918", true, true, true, F, false),
+    sp("192.0.0.0/24", "IETF Protocol Assignments", "RFC6890", false, false, false, F, false),
+    sp("192.0.0.0/29", "IPv4 Service Continuity Prefix", "RFC7335", true, true, true, F, false),
+    sp("192.0.0.8/32", "IPv4 dummy address", "RFC7600", true, false, false, F, false),
+    sp("192.0.0.9/32", "Port Control Protocol Anycast", "RFC7723", true, true, true, T, false),
+    sp("192.0.0.10/32", "Traversal Using Relays around NAT Anycast", "RFC8155", true, true, true, T, false),
+    sp("192.0.0.170/32", "NAT64/DNS64 Discovery", "RFC8880", false, false, false, F, true),
+    sp("192.0.0.171/32", "NAT64/DNS64 Discovery", "RFC8880", false, false, false, F, true),
+    sp("192.0.2.0/24", "Documentation (TEST-NET-1)", "RFC5737", false, false, false, F, false),
+    sp("192.31.196.0/24", "AS112-v4", "RFC7535", true, true, true, T, false),
+    sp("192.52.193.0/24", "AMT", "RFC7450", true, true, true, T, false),
+    sp("192.88.99.2/32", "6a44-relay anycast address", "RFC6751", true, true, true, F, false),
+    sp("192.168.0.0/16", "Private-Use", "RFC1918", true, true, true, F, false),
+    sp("192.175.48.0/24", "Direct Delegation AS112 Service", "RFC7534", true, true, true, T, false),
+    sp("198.18.0.0/15", "Benchmarking", "RFC2544", true, true, true, F, false),
+    sp("198.51.100.0/24", "Documentation (TEST-NET-2)", "RFC5737", false, false, false, F, false),
+    sp("203.0.113.0/24", "Documentation (TEST-NET-3)", "RFC5737", false, false, false, F, false),
+    sp("224.0.0.0/4", "Multicast", "RFC5771", false, true, true, None, false),
+    sp("240.0.0.0/4", "Reserved", "RFC1112", false, false, false, F, true),
+    sp("255.255.255.255/32", "Limited Broadcast", "RFC919", false, true, false, F, true),
+];
+
+// https://www.iana.org/assignments/iana-ipv6-special-registry/
+// ff00::/8 multicast is not part of the registry, but is listed here as well
+const IPV6_SPECIAL: &[SpecialPurpose] = &[
+    sp("::1/128", "Loopback Address", "RFC4291", false, false, false, F, true),
+    sp("::/128", "Unspecified Address", "RFC4291", true, false, false, F, true),
+    sp("::ffff:0:0/96", "IPv4-mapped Address", "RFC4291", false, false, false, F, true),
+    sp("64:ff9b::/96", "IPv4-IPv6 Translat.", "RFC6052", true, true, true, T, false),
+    sp("64:ff9b:1::/48", "IPv4-IPv6 Translat.", "RFC8215", true, true, true, F, false),
+    sp("100::/64", "Discard-Only Address Block", "RFC6666", true, true, true, F, false),
+    sp("2001::/23", "IETF Protocol Assignments", "RFC2928", false, false, false, F, false),
+    sp("2001::/32", "TEREDO", "RFC4380", true, true, true, None, false),
+    sp("2001:1::1/128", "Port Control Protocol Anycast", "RFC7723", true, true, true, T, false),
+    sp("2001:1::2/128", "Traversal Using Relays around NAT Anycast", "RFC8155", true, true, true, T, false),
+    sp("2001:1::3/128", "DNS-SD Service Registration Protocol Anycast", "RFC9665", true, true, true, T, false),
+    sp("2001:2::/48", "Benchmarking", "RFC5180", true, true, true, F, false),
+    sp("2001:3::/32", "AMT", "RFC7450", true, true, true, T, false),
+    sp("2001:4:112::/48", "AS112-v6", "RFC7535", true, true, true, T, false),
+    sp("2001:20::/28", "ORCHIDv2", "RFC7343", true, true, true, T, false),
+    sp("2001:30::/28", "Drone Remote ID Protocol Entity Tags (DETs) Prefix", "RFC9374", true, true, true, T, false),
+    sp("2001:db8::/32", "Documentation", "RFC3849", false, false, false, F, false),
+    sp("2002::/16", "6to4", "RFC3056", true, true, true, None, false),
+    sp("2620:4f:8000::/48", "Direct Delegation AS112 Service", "RFC7534", true, true, true, T, false),
+    sp("3fff::/20", "Documentation", "RFC9637", false, false, false, F, false),
+    sp("5f00::/16", "Segment Routing (SRv6) SIDs", "RFC9602", true, true, true, F, false),
+    sp("fc00::/7", "Unique-Local", "RFC4193", true, true, true, F, false),
+    sp("fe80::/10", "Link-Local Unicast", "RFC4291", true, true, false, F, true),
+    sp("ff00::/8", "Multicast", "RFC4291", false, true, true, None, false),
+];
+
+/// Returns the compiled-in special-purpose registry.
+///
+/// Some registered blocks are nested (eg. `192.0.0.0/29` in `192.0.0.0/24`), the more specific
+/// entry wins and the covering entry fills the remaining space.
+pub fn special_purpose() -> &'static IpTree<SpecialPurpose> {
+    static TREE: OnceLock<IpTree<SpecialPurpose>> = OnceLock::new();
+    TREE.get_or_init(|| {
+        let mut tree = IpTree::new();
+        for (key, value) in sorted(IPV4_SPECIAL) {
+            fill(&mut tree.ipv4, key, value);
+        }
+        for (key, value) in sorted(IPV6_SPECIAL) {
+            fill(&mut tree.ipv6, key, value);
+        }
+        tree
+    })
+}
+
+/// query [SpecialPurpose] of ip
+pub fn query(ip: IpAddr) -> Option<&'static SpecialPurpose> {
+    let tree = special_purpose();
+    match ip {
+        IpAddr::V4(ip) => tree.ipv4.query(ip.into()),
+        IpAddr::V6(ip) => tree.ipv6.query(ip.into()),
+    }
+}
+
+impl IpCodeMap {
+    /// query the special-purpose registry first, then [CountryRegionCode] of ip
+    ///
+    /// ```
+    /// use ip2c::rir::IpCodeMap;
+    /// use ip2c::special::Lookup;
+    ///
+    /// let map = IpCodeMap::new();
+    /// let r = map.query_special_first("::1".parse().unwrap()).unwrap();
+    /// assert!(matches!(r, Lookup::Special(sp) if sp.name == "Loopback Address"));
+    /// assert_eq!(map.query_special_first("1.1.1.1".parse().unwrap()), None);
+    /// ```
+    pub fn query_special_first(&self, ip: IpAddr) -> Option<Lookup> {
+        if let Some(sp) = query(ip) {
+            return Some(Lookup::Special(sp));
+        }
+        self.query(ip).map(Lookup::Code)
+    }
+}
+
+// more specific blocks first
+fn sorted<K>(list: &'static [SpecialPurpose]) -> Vec<(Interval<K>, SpecialPurpose)>
+    where K: Ord + Copy, Interval<K>: FromStr, <Interval<K> as FromStr>::Err: Debug {
+    let mut list: Vec<_> = list.iter().collect();
+    list.sort_by_key(|sp| Reverse(sp.block.rsplit('/').next().and_then(|n| n.parse::<u8>().ok())));
+    list.into_iter()
+        .map(|sp| (sp.block.parse().expect("invalid special-purpose block"), *sp))
+        .collect()
+}
+
+// insert the parts of `key` which are not covered yet
+fn fill<K: Discrete + Display>(tree: &mut IntervalTreeMap<K, SpecialPurpose>, key: Interval<K>, value: SpecialPurpose) {
+    let covered: Vec<Interval<K>> = tree.range(key).map(|(k, _)| *k).collect();
+    let mut rest = Some(key);
+    for k in covered {
+        let Some(r) = rest else { break };
+        let (before, after) = r.difference(&k);
+        if let Some(b) = before {
+            tree.insert(b, value).expect("special-purpose block conflict");
+        }
+        rest = after;
+    }
+    if let Some(r) = rest {
+        tree.insert(r, value).expect("special-purpose block conflict");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(ip: &str) -> Option<&'static str> {
+        query(ip.parse().unwrap()).map(|sp| sp.name)
+    }
+
+    #[test]
+    fn nested_blocks() {
+        assert_eq!(name("192.0.0.5"), Some("IPv4 Service Continuity Prefix"));
+        assert_eq!(name("192.0.0.8"), Some("IPv4 dummy address"));
+        assert_eq!(name("192.0.0.100"), Some("IETF Protocol Assignments"));
+        assert_eq!(name("0.0.0.0"), Some("This host on this network"));
+        assert_eq!(name("0.1.2.3"), Some("This network"));
+        assert_eq!(name("255.255.255.255"), Some("Limited Broadcast"));
+        assert_eq!(name("255.255.255.254"), Some("Reserved"));
+        assert_eq!(name("2001::1"), Some("TEREDO"));
+        assert_eq!(name("2001:1::2"), Some("Traversal Using Relays around NAT Anycast"));
+        assert_eq!(name("2001:1::4"), Some("IETF Protocol Assignments"));
+        assert_eq!(name("2001:1ff::"), Some("IETF Protocol Assignments"));
+        assert_eq!(name("2001:200::"), None);
+    }
+
+    #[test]
+    fn common_blocks() {
+        assert_eq!(name("100.64.0.1"), Some("Shared Address Space"));
+        assert_eq!(name("100.128.0.1"), None);
+        assert_eq!(name("198.19.255.255"), Some("Benchmarking"));
+        assert_eq!(name("239.1.1.1"), Some("Multicast"));
+        assert_eq!(name("fe80::1"), Some("Link-Local Unicast"));
+        assert_eq!(name("fd00::1"), Some("Unique-Local"));
+        assert_eq!(name("::ffff:1.2.3.4"), Some("IPv4-mapped Address"));
+        assert_eq!(name("2400:cb00::1"), None);
+        let sp = query("127.0.0.1".parse().unwrap()).unwrap();
+        assert!(!sp.forwardable && sp.reserved_by_protocol);
+        assert_eq!(sp.globally_reachable, Some(false));
+    }
+}
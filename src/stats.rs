@@ -0,0 +1,191 @@
+//! Coverage statistics, eg. how many IPv4 addresses are assigned per country.
+//!
+//! ```
+//! use ip2c::rir::{CountryRegionCode, IpCodeMap};
+//!
+//! let cn = CountryRegionCode::new("CN").unwrap();
+//! let mut map = IpCodeMap::new();
+//! map.ipv4.insert("1.0.1.0/24".parse().unwrap(), cn).unwrap();
+//! map.ipv4.insert("1.0.2.0/23".parse().unwrap(), cn).unwrap();
+//! map.ipv6.insert("2001:db8::/32".parse().unwrap(), cn).unwrap();
+//!
+//! let stats = map.coverage_by_code();
+//! assert_eq!(stats[&cn].ipv4.addresses, 768);
+//! assert_eq!(stats[&cn].ipv4.intervals, 2);
+//! assert_eq!(stats[&cn].ipv4.slash24(), 3.0);
+//! assert_eq!(stats[&cn].ipv6.slash48(), 65536.0);
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::{Interval, IntervalTreeMap, IpAddress, IPv4, IPv6};
+use crate::rir::{CountryRegionCode, IpCodeMap};
+
+/// The exact address count of the family, `u64` for IPv4 and `u128` for IPv6
+pub trait AddressCount: IpAddress {
+    type Count: Copy + Debug + Default + Eq + Ord + Hash;
+
+    /// the number of addresses of the interval, see [Interval::size]
+    fn count(k: &Interval<Self>) -> Self::Count;
+
+    /// saturated to the max of the count
+    fn add(a: Self::Count, b: Self::Count) -> Self::Count;
+
+    fn to_f64(c: Self::Count) -> f64;
+}
+
+impl AddressCount for IPv4 {
+    type Count = u64;
+
+    fn count(k: &Interval<Self>) -> u64 {
+        k.size()
+    }
+
+    fn add(a: u64, b: u64) -> u64 {
+        a.saturating_add(b)
+    }
+
+    fn to_f64(c: u64) -> f64 {
+        c as f64
+    }
+}
+
+impl AddressCount for IPv6 {
+    type Count = u128;
+
+    fn count(k: &Interval<Self>) -> u128 {
+        k.size()
+    }
+
+    fn add(a: u128, b: u128) -> u128 {
+        a.saturating_add(b)
+    }
+
+    fn to_f64(c: u128) -> f64 {
+        c as f64
+    }
+}
+
+/// The number of addresses and intervals
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Coverage<K: AddressCount> {
+    /// saturated to `u128::MAX` for the whole IPv6 space
+    pub addresses: K::Count,
+    pub intervals: usize,
+}
+
+impl<K: AddressCount> Default for Coverage<K> {
+    fn default() -> Self {
+        Coverage { addresses: K::Count::default(), intervals: 0 }
+    }
+}
+
+impl<K: AddressCount> Coverage<K> {
+    fn add(&mut self, k: &Interval<K>) {
+        self.addresses = K::add(self.addresses, K::count(k));
+        self.intervals += 1;
+    }
+
+    /// the number of `/len` blocks the addresses are equal to
+    pub fn equivalents(&self, len: u8) -> f64 {
+        K::to_f64(self.addresses) / 2f64.powi((K::BITS - len.min(K::BITS)) as i32)
+    }
+
+    /// the fraction of the whole address space
+    pub fn ratio(&self) -> f64 {
+        self.equivalents(0)
+    }
+}
+
+impl Coverage<IPv4> {
+    /// the number of `/24` blocks the addresses are equal to
+    pub fn slash24(&self) -> f64 {
+        self.equivalents(24)
+    }
+}
+
+impl Coverage<IPv6> {
+    /// the number of `/48` blocks the addresses are equal to
+    pub fn slash48(&self) -> f64 {
+        self.equivalents(48)
+    }
+}
+
+impl<K: AddressCount, V> IntervalTreeMap<K, V> {
+    /// The total coverage of the key space.
+    ///
+    /// ```
+    /// use ip2c::Ipv4Tree;
+    ///
+    /// let mut map = Ipv4Tree::new();
+    /// map.insert("0.0.0.0/1".parse().unwrap(), ()).unwrap();
+    /// assert_eq!(map.coverage().addresses, 1 << 31);
+    /// assert_eq!(map.coverage().ratio(), 0.5);
+    /// ```
+    pub fn coverage(&self) -> Coverage<K> {
+        let mut c = Coverage::default();
+        for k in self.keys() {
+            c.add(k);
+        }
+        c
+    }
+
+    /// The coverage of each value.
+    pub fn coverage_by_value(&self) -> HashMap<&V, Coverage<K>> where V: Hash + Eq {
+        let mut m: HashMap<&V, Coverage<K>> = HashMap::new();
+        for (k, v) in self.iter() {
+            m.entry(v).or_default().add(k);
+        }
+        m
+    }
+}
+
+/// The coverage of a [CountryRegionCode], see [IpCodeMap::coverage_by_code]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CodeCoverage {
+    pub ipv4: Coverage<IPv4>,
+    pub ipv6: Coverage<IPv6>,
+}
+
+impl IpCodeMap {
+    /// The coverage of each [CountryRegionCode], sorted by code.
+    pub fn coverage_by_code(&self) -> BTreeMap<CountryRegionCode, CodeCoverage> {
+        let mut m: BTreeMap<CountryRegionCode, CodeCoverage> = BTreeMap::new();
+        for (code, c) in self.ipv4.coverage_by_value() {
+            m.entry(*code).or_default().ipv4 = c;
+        }
+        for (code, c) in self.ipv6.coverage_by_value() {
+            m.entry(*code).or_default().ipv6 = c;
+        }
+        m
+    }
+
+    /// The total coverage of IPv4 and IPv6.
+    pub fn coverage(&self) -> CodeCoverage {
+        CodeCoverage {
+            ipv4: self.ipv4.coverage(),
+            ipv6: self.ipv6.coverage(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Ipv6Tree, Ipv4Tree};
+
+    #[test]
+    fn whole_space() {
+        let mut v4 = Ipv4Tree::new();
+        v4.insert("0.0.0.0/0".parse().unwrap(), 'a').unwrap();
+        let c: u64 = v4.coverage().addresses;
+        assert_eq!(c, 1 << 32);
+        assert_eq!(v4.coverage().slash24(), (1 << 24) as f64);
+        let mut v6 = Ipv6Tree::new();
+        v6.insert("::/0".parse().unwrap(), 'a').unwrap();
+        assert_eq!(v6.coverage().addresses, u128::MAX);
+        assert_eq!(v6.coverage().ratio(), 1.0);
+        assert_eq!(v6.coverage_by_value()[&'a'].intervals, 1);
+    }
+}
@@ -1,14 +1,99 @@
 use std::error::Error;
-use std::fmt::{Debug, Display, Formatter};
+use std::fmt::{Debug, Display, Formatter, Write as _};
+use std::ops::Range;
 use std::str::FromStr;
-use crate::{Interval, IPv4, IPv6};
+use crate::{Cidr, Interval, IPv4, IPv6};
 
-#[derive(Debug)]
-pub struct ParseIpv4ScopeError;
+/// The offending input of a parse error, and the byte range of the bad part in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorInput {
+    pub input: String,
+    pub span: Range<usize>,
+}
+
+impl ErrorInput {
+    // `part` must be a sub slice of `s`
+    fn new(s: &str, part: &str) -> Self {
+        let start = part.as_ptr() as usize - s.as_ptr() as usize;
+        ErrorInput { input: s.to_string(), span: start..start + part.len() }
+    }
+
+    // the part after the first `max` bytes, from the char which crosses the limit
+    fn beyond(s: &str, max: usize) -> Self {
+        let start = (0..=max).rev().find(|&i| s.is_char_boundary(i)).unwrap_or(0);
+        ErrorInput::new(s, &s[start..])
+    }
+
+    /// the bad part of the input
+    pub fn part(&self) -> &str {
+        &self.input[self.span.clone()]
+    }
+}
+
+impl Display for ErrorInput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?} at {}..{} of {:?}", self.part(), self.span.start, self.span.end, self.input))
+    }
+}
+
+/// ```
+/// use ip2c::{Interval, IPv4};
+/// use ip2c::util::ParseIpv4ScopeError;
+///
+/// let e = "1.2.3.4/33".parse::<Interval<IPv4>>().unwrap_err();
+/// assert!(matches!(e, ParseIpv4ScopeError::BadPrefixLength(_)));
+/// assert_eq!(e.input().span, 8..10);
+/// assert_eq!(e.to_string(), r#"parse ipv4 scope error: bad prefix length "33" at 8..10 of "1.2.3.4/33""#);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseIpv4ScopeError {
+    BadAddress(ErrorInput),
+    BadPrefixLength(ErrorInput),
+    /// the dotted netmask or wildcard
+    BadMask(ErrorInput),
+    /// an octet of the glob, per-octet range or `a-N` form
+    BadOctet(ErrorInput),
+    EndBeforeStart(ErrorInput),
+    TooLong(ErrorInput),
+    HostBitsSet(ErrorInput),
+    /// the pattern covers non-contiguous addresses, but one interval is expected
+    NonContiguous(ErrorInput),
+    TooManyRanges(ErrorInput),
+}
+
+impl ParseIpv4ScopeError {
+    pub fn input(&self) -> &ErrorInput {
+        match self {
+            ParseIpv4ScopeError::BadAddress(e) |
+            ParseIpv4ScopeError::BadPrefixLength(e) |
+            ParseIpv4ScopeError::BadMask(e) |
+            ParseIpv4ScopeError::BadOctet(e) |
+            ParseIpv4ScopeError::EndBeforeStart(e) |
+            ParseIpv4ScopeError::TooLong(e) |
+            ParseIpv4ScopeError::HostBitsSet(e) |
+            ParseIpv4ScopeError::NonContiguous(e) |
+            ParseIpv4ScopeError::TooManyRanges(e) => e,
+        }
+    }
+
+    fn message(&self) -> &'static str {
+        match self {
+            ParseIpv4ScopeError::BadAddress(_) => "bad address",
+            ParseIpv4ScopeError::BadPrefixLength(_) => "bad prefix length",
+            ParseIpv4ScopeError::BadMask(_) => "bad mask",
+            ParseIpv4ScopeError::BadOctet(_) => "bad octet",
+            ParseIpv4ScopeError::EndBeforeStart(_) => "end is before start",
+            ParseIpv4ScopeError::TooLong(_) => "too long",
+            ParseIpv4ScopeError::HostBitsSet(_) => "host bits are set",
+            ParseIpv4ScopeError::NonContiguous(_) => "non-contiguous pattern",
+            ParseIpv4ScopeError::TooManyRanges(_) => "too many ranges",
+        }
+    }
+}
 
 impl Display for ParseIpv4ScopeError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str("parse ipv4 scope error")
+        f.write_fmt(format_args!("parse ipv4 scope error: {} {}", self.message(), self.input()))
     }
 }
 
@@ -19,8 +104,11 @@ impl FromStr for Interval<IPv4> {
 
     /// eg: parse 100.0.0.1-100 to Scope(100.0.0.1, 100.0.0.100)
     /// eg: parse 100.0.0.0-101.0.0.0 to Scope(100.0.0.0, 101.0.0.0)
-    /// eg: parse 100.0.0.1/20 to Scope(100.0.0.0, 100.0.15.255)
+    /// eg: parse 100.0.0.1/20 to Scope(100.0.0.0, 100.0.15.255), and 100.0.0.1/0 to Scope(0.0.0.0, 255.255.255.255)
     /// eg: parse 100.0.0.200 to Scope(100.0.0.200, 100.0.0.200)
+    /// eg: parse 10.0.0.0/255.255.0.0, 10.0.0.0 0.0.255.255, 10.0.*.* or 10.0.0-255.* to Scope(10.0.0.0, 10.0.255.255)
+    ///
+    /// Patterns that cover non-contiguous addresses (eg. `10.*.0.1`) are rejected here, use [parse_ipv4_ranges] for them.
     ///
     /// ```
     /// use std::str::FromStr;
@@ -49,77 +137,307 @@ impl FromStr for Interval<IPv4> {
     /// let ip1 = IPv4::from_str("100.0.0.200").unwrap();
     /// println!("{}", v.to_string());
     /// assert_eq!(v, Interval(ip, ip1));
+    ///
+    /// let v = Interval::<IPv4>::from_str("10.1.0-3.*").unwrap();
+    /// let ip = IPv4::from_str("10.1.0.0").unwrap();
+    /// let ip1 = IPv4::from_str("10.1.3.255").unwrap();
+    /// assert_eq!(v, Interval(ip, ip1));
+    /// assert_eq!(Interval::<IPv4>::from_str("10.0.0.0/255.252.0.0").unwrap(), "10.0.0.0/14".parse().unwrap());
+    /// assert_eq!(Interval::<IPv4>::from_str("10.0.0.0 0.3.255.255").unwrap(), "10.0.0.0/14".parse().unwrap());
+    /// assert!(Interval::<IPv4>::from_str("10.*.0.1").is_err());
     /// ```
     ///
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use ParseIpv4ScopeError::*;
         if s.len() > 31 {
-            return Err(ParseIpv4ScopeError);
+            return Err(TooLong(ErrorInput::beyond(s, 31)));
         };
-        return if let Some(i) = s.find('-') {
-            let (a, b) = s.split_at(i);
-            let b = &b[1..];
-            let Ok(ip) = IPv4::from_str(a) else {
-                return Err(ParseIpv4ScopeError);
+        if is_ipv4_pattern(s) {
+            return match parse_ipv4_pattern(s)?.as_slice() {
+                [v] => Ok(*v),
+                _ => Err(NonContiguous(ErrorInput::new(s, s))),
             };
+        }
+        if let Some((a, b)) = s.split_once('-') {
+            let ip = parse_ipv4(s, a)?;
             if b.contains('.') {
-                let Ok(ip1) = IPv4::from_str(b) else {
-                    return Err(ParseIpv4ScopeError);
-                };
+                let ip1 = parse_ipv4(s, b)?;
                 if ip1.0 < ip.0 {
-                    return Err(ParseIpv4ScopeError);
+                    return Err(EndBeforeStart(ErrorInput::new(s, b)));
                 }
                 Ok(Interval(ip, ip1))
             } else {
                 let Ok(n) = b.parse::<u8>() else {
-                    return Err(ParseIpv4ScopeError);
+                    return Err(BadOctet(ErrorInput::new(s, b)));
                 };
                 let ip1 = IPv4((ip.0 & !255) + (n as u32));
                 if ip1.0 < ip.0 {
-                    return Err(ParseIpv4ScopeError);
+                    return Err(EndBeforeStart(ErrorInput::new(s, b)));
                 }
                 Ok(Interval(ip, ip1))
             }
-        } else if let Some(i) = s.find('/') {
-            let (a, b) = s.split_at(i);
-            let b = &b[1..];
-            let Ok(ip) = IPv4::from_str(a) else {
-                return Err(ParseIpv4ScopeError);
-            };
-            let Ok(n) = b.parse::<u8>() else {
-                return Err(ParseIpv4ScopeError);
+        } else if s.contains('/') {
+            // the host bits are masked, like `Cidr::parse_lenient`
+            Ok(Cidr::<IPv4>::parse_lenient(s)?.into())
+        } else {
+            let ip = parse_ipv4(s, s)?;
+            Ok(Interval(ip, ip))
+        }
+    }
+}
+
+// parse the `part` of `s`
+fn parse_ipv4(s: &str, part: &str) -> Result<IPv4, ParseIpv4ScopeError> {
+    let Ok(ip) = IPv4::from_str(part) else {
+        return Err(ParseIpv4ScopeError::BadAddress(ErrorInput::new(s, part)));
+    };
+    Ok(ip)
+}
+
+/// Parse an IPv4 pattern to a sorted list of intervals.
+///
+/// Accepts everything [Interval<IPv4>::from_str] does, and also the patterns covering non-contiguous addresses:
+/// - dotted netmask: `10.0.0.0/255.255.0.0`
+/// - Cisco wildcard: `10.0.0.0 0.0.255.255`
+/// - octet globs: `10.1.*.*`
+/// - per-octet ranges: `10.1.0-3.*`
+///
+/// ```
+/// use ip2c::util::parse_ipv4_ranges;
+///
+/// let v = parse_ipv4_ranges("10.1-2.0.*").unwrap();
+/// assert_eq!(v, vec!["10.1.0.0/24".parse().unwrap(), "10.2.0.0/24".parse().unwrap()]);
+/// let v = parse_ipv4_ranges("10.0.0.0 0.0.1.3").unwrap();
+/// assert_eq!(v, vec!["10.0.0.0-3".parse().unwrap(), "10.0.1.0-3".parse().unwrap()]);
+/// assert_eq!(parse_ipv4_ranges("10.0.0.0/8").unwrap().len(), 1);
+/// ```
+pub fn parse_ipv4_ranges(s: &str) -> Result<Vec<Interval<IPv4>>, ParseIpv4ScopeError> {
+    if s.len() > 31 {
+        return Err(ParseIpv4ScopeError::TooLong(ErrorInput::beyond(s, 31)));
+    };
+    if is_ipv4_pattern(s) {
+        parse_ipv4_pattern(s)
+    } else {
+        Ok(vec![Interval::<IPv4>::from_str(s)?])
+    }
+}
+
+// more than this number of intervals is treated as an error, eg. `*.*.*.1`
+const MAX_IPV4_RANGES: u64 = 1 << 16;
+
+fn is_ipv4_pattern(s: &str) -> bool {
+    if s.contains(' ') || s.contains('*') {
+        return true;
+    }
+    if let Some((_, mask)) = s.split_once('/') {
+        return mask.contains('.');
+    }
+    // the `-` in the last octet is the `a-N` form
+    let octets: Vec<&str> = s.split('.').collect();
+    octets.len() == 4 && octets[..3].iter().any(|o| o.contains('-'))
+}
+
+fn parse_ipv4_pattern(s: &str) -> Result<Vec<Interval<IPv4>>, ParseIpv4ScopeError> {
+    use ParseIpv4ScopeError::*;
+    let (ip, wildcard, w) = if let Some((a, w)) = s.split_once(' ') {
+        let ip = parse_ipv4(s, a.trim())?;
+        let Ok(wildcard) = IPv4::from_str(w.trim()) else {
+            return Err(BadMask(ErrorInput::new(s, w)));
+        };
+        (ip, wildcard.0, w)
+    } else if let Some((a, m)) = s.split_once('/') {
+        let ip = parse_ipv4(s, a)?;
+        let Ok(mask) = IPv4::from_str(m) else {
+            return Err(BadMask(ErrorInput::new(s, m)));
+        };
+        (ip, !mask.0, m)
+    } else {
+        return octet_ranges(s);
+    };
+    masked_ranges(ip.0, wildcard).ok_or_else(|| TooManyRanges(ErrorInput::new(s, w)))
+}
+
+// all of `ip` with any bits of `wildcard`, `None` if too many
+fn masked_ranges(ip: u32, wildcard: u32) -> Option<Vec<Interval<IPv4>>> {
+    // the trailing ones are contiguous
+    let low = wildcard & !wildcard.wrapping_add(1);
+    let high = wildcard & !low;
+    if 1u64 << high.count_ones() > MAX_IPV4_RANGES {
+        return None;
+    }
+    let base = ip & !wildcard;
+    let mut v = Vec::with_capacity(1 << high.count_ones());
+    let mut sub: u32 = 0;
+    loop {
+        v.push(Interval(IPv4(base | sub), IPv4(base | sub | low)));
+        if sub == high {
+            break;
+        }
+        // next subset of `high` in ascending order
+        sub = (sub | !high).wrapping_add(1) & high;
+    }
+    Some(v)
+}
+
+// eg. 10.1.0-3.*
+fn octet_ranges(s: &str) -> Result<Vec<Interval<IPv4>>, ParseIpv4ScopeError> {
+    use ParseIpv4ScopeError::*;
+    let mut octets = [(0u8, 0u8); 4];
+    let mut n = 0;
+    for part in s.split('.') {
+        if n >= 4 {
+            return Err(BadAddress(ErrorInput::new(s, s)));
+        }
+        octets[n] = if part == "*" {
+            (0, 255)
+        } else if let Some((a, b)) = part.split_once('-') {
+            let (Ok(a), Ok(b)) = (a.parse::<u8>(), b.parse::<u8>()) else {
+                return Err(BadOctet(ErrorInput::new(s, part)));
             };
-            if n == 0 {
-                if ip.0 == 0 {
-                    Ok(Interval(IPv4(0), IPv4(u32::MAX)))
-                } else {
-                    Err(ParseIpv4ScopeError)
-                }
-            } else if n == 32 {
-                Ok(Interval(ip, ip))
-            } else if n < 32 {
-                let mask: u32 = !((1 << (32 - n)) - 1);
-                let ip0 = IPv4(ip.0 & mask);
-                let ip1 = IPv4(ip.0 | !mask);
-                Ok(Interval(ip0, ip1))
-            } else {
-                Err(ParseIpv4ScopeError)
+            if b < a {
+                return Err(EndBeforeStart(ErrorInput::new(s, part)));
             }
+            (a, b)
         } else {
-            let Ok(ip) = IPv4::from_str(s) else {
-                return Err(ParseIpv4ScopeError);
+            let Ok(a) = part.parse::<u8>() else {
+                return Err(BadOctet(ErrorInput::new(s, part)));
             };
-            Ok(Interval(ip, ip))
+            (a, a)
         };
+        n += 1;
+    }
+    if n != 4 {
+        return Err(BadAddress(ErrorInput::new(s, s)));
+    }
+    // octets after `k` are full, so the octet `k` is the last one can be expressed in one interval
+    let mut k = 3;
+    while k > 0 && octets[k] == (0, 255) {
+        k -= 1;
+    }
+    let count: u64 = octets[..k].iter().map(|(a, b)| (*b - *a) as u64 + 1).product();
+    if count > MAX_IPV4_RANGES {
+        return Err(TooManyRanges(ErrorInput::new(s, s)));
+    }
+    let shift = 8 * (3 - k as u32);
+    let rest = (1u32 << shift) - 1;
+    let mut v = Vec::with_capacity(count as usize);
+    let mut prefix = vec![0u32];
+    for (a, b) in &octets[..k] {
+        prefix = prefix.iter()
+            .flat_map(|p| (*a..=*b).map(move |o| (p << 8) | o as u32))
+            .collect();
     }
+    for p in prefix {
+        let hi = if k == 0 { 0 } else { p << (32 - 8 * k as u32) };
+        let (a, b) = octets[k];
+        let start = hi | ((a as u32) << shift);
+        let end = hi | ((b as u32) << shift) | rest;
+        v.push(Interval(IPv4(start), IPv4(end)));
+    }
+    Ok(v)
 }
 
+impl FromStr for Cidr<IPv4> {
+    type Err = ParseIpv4ScopeError;
+
+    /// Strict parse `a/len` or a single address, the host bits must be zero.
+    ///
+    /// ```
+    /// use ip2c::{Cidr, IPv4};
+    ///
+    /// assert!("100.0.0.0/20".parse::<Cidr<IPv4>>().is_ok());
+    /// assert!("100.0.0.1/20".parse::<Cidr<IPv4>>().is_err());
+    /// assert!("1.2.3.4/0".parse::<Cidr<IPv4>>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (ip, n) = split_ipv4_cidr(s)?;
+        Cidr::new(ip, n).ok_or_else(|| {
+            let a = s.split_once('/').map_or(s, |(a, _)| a);
+            ParseIpv4ScopeError::HostBitsSet(ErrorInput::new(s, a))
+        })
+    }
+}
+
+impl Cidr<IPv4> {
+    /// Parse `a/len` or a single address, the host bits are masked.
+    ///
+    /// ```
+    /// use ip2c::{Cidr, IPv4};
+    ///
+    /// assert_eq!(Cidr::<IPv4>::parse_lenient("1.2.3.4/0").unwrap().to_string(), "0.0.0.0/0");
+    /// ```
+    pub fn parse_lenient(s: &str) -> Result<Self, ParseIpv4ScopeError> {
+        let (ip, n) = split_ipv4_cidr(s)?;
+        Ok(Cidr::new_lenient(ip, n).expect("prefix length is checked"))
+    }
+}
+
+fn split_ipv4_cidr(s: &str) -> Result<(IPv4, u8), ParseIpv4ScopeError> {
+    let (a, n) = match s.split_once('/') {
+        Some((a, b)) => {
+            match b.parse::<u8>() {
+                Ok(n) if n <= 32 => (a, n),
+                _ => return Err(ParseIpv4ScopeError::BadPrefixLength(ErrorInput::new(s, b))),
+            }
+        }
+        None => (s, 32),
+    };
+    Ok((parse_ipv4(s, a)?, n))
+}
+
+/// ```
+/// use ip2c::{Interval, IPv6};
+/// use ip2c::util::ParseIpv6ScopeError;
+///
+/// let e = "fe80::1%eth0".parse::<Interval<IPv6>>().unwrap_err();
+/// assert!(matches!(e, ParseIpv6ScopeError::ZoneId(_)));
+/// assert_eq!(e.input().part(), "%eth0");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseIpv6ScopeError {
+    BadAddress(ErrorInput),
+    BadPrefixLength(ErrorInput),
+    /// the last hextet of the `start-lastHextet` shorthand
+    BadHextet(ErrorInput),
+    EndBeforeStart(ErrorInput),
+    TooLong(ErrorInput),
+    HostBitsSet(ErrorInput),
+    /// zone ids (eg. `%eth0`) are not part of the address space
+    ZoneId(ErrorInput),
+    UnbalancedBrackets(ErrorInput),
+}
+
+impl ParseIpv6ScopeError {
+    pub fn input(&self) -> &ErrorInput {
+        match self {
+            ParseIpv6ScopeError::BadAddress(e) |
+            ParseIpv6ScopeError::BadPrefixLength(e) |
+            ParseIpv6ScopeError::BadHextet(e) |
+            ParseIpv6ScopeError::EndBeforeStart(e) |
+            ParseIpv6ScopeError::TooLong(e) |
+            ParseIpv6ScopeError::HostBitsSet(e) |
+            ParseIpv6ScopeError::ZoneId(e) |
+            ParseIpv6ScopeError::UnbalancedBrackets(e) => e,
+        }
+    }
 
-#[derive(Debug)]
-pub struct ParseIpv6ScopeError;
+    fn message(&self) -> &'static str {
+        match self {
+            ParseIpv6ScopeError::BadAddress(_) => "bad address",
+            ParseIpv6ScopeError::BadPrefixLength(_) => "bad prefix length",
+            ParseIpv6ScopeError::BadHextet(_) => "bad last hextet",
+            ParseIpv6ScopeError::EndBeforeStart(_) => "end is before start",
+            ParseIpv6ScopeError::TooLong(_) => "too long",
+            ParseIpv6ScopeError::HostBitsSet(_) => "host bits are set",
+            ParseIpv6ScopeError::ZoneId(_) => "zone id is not supported",
+            ParseIpv6ScopeError::UnbalancedBrackets(_) => "unbalanced brackets",
+        }
+    }
+}
 
 impl Display for ParseIpv6ScopeError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str("parse ipv6 scope error")
+        f.write_fmt(format_args!("parse ipv6 scope error: {} {}", self.message(), self.input()))
     }
 }
 
@@ -129,6 +447,10 @@ impl FromStr for Interval<IPv6> {
     type Err = ParseIpv6ScopeError;
 
     /// eg: parse from ipv6 or ipv6 network segment
+    /// eg: parse 2001:db8::1-2001:db8::ff or 2001:db8::1-ff to Scope(2001:db8::1, 2001:db8::ff)
+    /// eg: parse [2001:db8::1], [2001:db8::]/32 or [2001:db8::1]-[2001:db8::ff] like the URL host form
+    ///
+    /// Zone ids (eg. `fe80::1%eth0`) are rejected, since they are not part of the address space.
     /// ```
     /// use std::str::FromStr;
     /// use ip2c::{Interval, IPv6};
@@ -138,41 +460,307 @@ impl FromStr for Interval<IPv6> {
     /// let ip1 = IPv6::from_str("::0.0.0.255").unwrap();
     /// println!("{}", v.to_string());
     /// assert_eq!(v, Interval(ip, ip1));
+    ///
+    /// let v = Interval::<IPv6>::from_str("2001:db8::1-2001:db8::1:0").unwrap();
+    /// let ip = IPv6::from_str("2001:db8::1").unwrap();
+    /// let ip1 = IPv6::from_str("2001:db8::1:0").unwrap();
+    /// assert_eq!(v, Interval(ip, ip1));
+    ///
+    /// let v = Interval::<IPv6>::from_str("[2001:db8::1]-ff").unwrap();
+    /// let ip1 = IPv6::from_str("2001:db8::ff").unwrap();
+    /// assert_eq!(v, Interval(ip, ip1));
+    ///
+    /// assert_eq!(Interval::<IPv6>::from_str("[2001:db8::]/32").unwrap(), "2001:db8::/32".parse().unwrap());
+    /// let e = Interval::<IPv6>::from_str("fe80::1%eth0").unwrap_err();
+    /// assert_eq!(e.to_string(), r#"parse ipv6 scope error: zone id is not supported "%eth0" at 7..12 of "fe80::1%eth0""#);
+    /// assert!(Interval::<IPv6>::from_str("2001:db8::ff-1").is_err());
     /// ```
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() > 48 || !s.contains(':') {
-            return Err(ParseIpv6ScopeError);
+        use ParseIpv6ScopeError::*;
+        if s.len() > 90 {
+            return Err(TooLong(ErrorInput::beyond(s, 90)));
         };
-        return if let Some(i) = s.find('/') {
-            let (a, b) = s.split_at(i);
-            let b = &b[1..];
-            let Ok(ip) = IPv6::from_str(a) else {
-                return Err(ParseIpv6ScopeError);
-            };
-            let Ok(n) = b.parse::<u8>() else {
-                return Err(ParseIpv6ScopeError);
-            };
-            if n == 0 {
-                if ip.0 == 0 {
-                    Ok(Interval(IPv6(0), IPv6(u128::MAX)))
-                } else {
-                    Err(ParseIpv6ScopeError)
-                }
-            } else if n == 128 {
-                Ok(Interval(ip, ip))
-            } else if n < 128 {
-                let mask: u128 = !((1 << (128 - n)) - 1);
-                let ip0 = IPv6(ip.0 & mask);
-                let ip1 = IPv6(ip.0 | !mask);
-                Ok(Interval(ip0, ip1))
+        if !s.contains(':') {
+            return Err(BadAddress(ErrorInput::new(s, s)));
+        }
+        reject_zone_id(s)?;
+        if let Some((a, b)) = s.split_once('-') {
+            let ip = parse_ipv6(s, a)?;
+            let end = strip_brackets(s, b)?;
+            let ip1 = if end.contains(':') {
+                parse_ipv6(s, b)?
             } else {
-                Err(ParseIpv6ScopeError)
+                // the last hextet shorthand, eg. 2001:db8::1-ff
+                if end.is_empty() || end.len() > 4 {
+                    return Err(BadHextet(ErrorInput::new(s, b)));
+                }
+                let Ok(n) = u16::from_str_radix(end, 16) else {
+                    return Err(BadHextet(ErrorInput::new(s, b)));
+                };
+                IPv6((ip.0 & !0xffff) | n as u128)
+            };
+            if ip1.0 < ip.0 {
+                return Err(EndBeforeStart(ErrorInput::new(s, b)));
             }
+            Ok(Interval(ip, ip1))
+        } else if s.contains('/') {
+            // the host bits are masked, like `Cidr::parse_lenient`
+            Ok(Cidr::<IPv6>::parse_lenient(s)?.into())
         } else {
-            let Ok(ip) = IPv6::from_str(s) else {
-                return Err(ParseIpv6ScopeError);
-            };
+            let ip = parse_ipv6(s, s)?;
             Ok(Interval(ip, ip))
-        };
+        }
+    }
+}
+
+fn reject_zone_id(s: &str) -> Result<(), ParseIpv6ScopeError> {
+    if let Some(i) = s.find('%') {
+        let zone = s[i..].split(['/', '-', ']']).next().unwrap_or_default();
+        return Err(ParseIpv6ScopeError::ZoneId(ErrorInput::new(s, zone)));
+    }
+    Ok(())
+}
+
+// `[2001:db8::1]` to `2001:db8::1`
+fn strip_brackets<'a>(s: &str, part: &'a str) -> Result<&'a str, ParseIpv6ScopeError> {
+    match (part.strip_prefix('['), part.strip_suffix(']')) {
+        (Some(_), Some(_)) if part.len() >= 2 => Ok(&part[1..part.len() - 1]),
+        (None, None) => Ok(part),
+        _ => Err(ParseIpv6ScopeError::UnbalancedBrackets(ErrorInput::new(s, part))),
+    }
+}
+
+// parse the `part` of `s`
+fn parse_ipv6(s: &str, part: &str) -> Result<IPv6, ParseIpv6ScopeError> {
+    let Ok(ip) = IPv6::from_str(strip_brackets(s, part)?) else {
+        return Err(ParseIpv6ScopeError::BadAddress(ErrorInput::new(s, part)));
+    };
+    Ok(ip)
+}
+
+impl FromStr for Cidr<IPv6> {
+    type Err = ParseIpv6ScopeError;
+
+    /// Strict parse `a/len` or a single address, the host bits must be zero.
+    ///
+    /// ```
+    /// use ip2c::{Cidr, IPv6};
+    ///
+    /// assert!("2001:db8::/32".parse::<Cidr<IPv6>>().is_ok());
+    /// assert!("[2001:db8::]/32".parse::<Cidr<IPv6>>().is_ok());
+    /// let e = "2001:db8::1/32".parse::<Cidr<IPv6>>().unwrap_err();
+    /// assert_eq!(e.to_string(), r#"parse ipv6 scope error: host bits are set "2001:db8::1" at 0..11 of "2001:db8::1/32""#);
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (ip, n) = split_ipv6_cidr(s)?;
+        Cidr::new(ip, n).ok_or_else(|| {
+            let a = s.split_once('/').map_or(s, |(a, _)| a);
+            ParseIpv6ScopeError::HostBitsSet(ErrorInput::new(s, a))
+        })
+    }
+}
+
+impl Cidr<IPv6> {
+    /// Parse `a/len` or a single address, the host bits are masked.
+    ///
+    /// ```
+    /// use ip2c::{Cidr, IPv6};
+    ///
+    /// assert_eq!(Cidr::<IPv6>::parse_lenient("2001:db8::1/32").unwrap().to_string(), "2001:db8::/32");
+    /// ```
+    pub fn parse_lenient(s: &str) -> Result<Self, ParseIpv6ScopeError> {
+        let (ip, n) = split_ipv6_cidr(s)?;
+        Ok(Cidr::new_lenient(ip, n).expect("prefix length is checked"))
+    }
+}
+
+fn split_ipv6_cidr(s: &str) -> Result<(IPv6, u8), ParseIpv6ScopeError> {
+    reject_zone_id(s)?;
+    let (a, n) = match s.split_once('/') {
+        Some((a, b)) => {
+            match b.parse::<u8>() {
+                Ok(n) if n <= 128 => (a, n),
+                _ => return Err(ParseIpv6ScopeError::BadPrefixLength(ErrorInput::new(s, b))),
+            }
+        }
+        None => (s, 128),
+    };
+    Ok((parse_ipv6(s, a)?, n))
+}
+
+/// Find the IPv4 and IPv6 literals in a line of text, eg. a log line, yields the byte ranges and the addresses.
+///
+/// The text is scanned as bytes, so the line need not be UTF-8. An address must not be glued to letters or digits,
+/// and the port (`1.2.3.4:80`), brackets (`[::1]:443`) and the trailing dot of a sentence are not part of it.
+///
+/// ```
+/// use ip2c::util::find_ips;
+///
+/// let line = b"1.2.3.4 - - \"GET / HTTP/1.1\" from [2001:db8::1]:443, v1.2.3 10.0.0.1:8080.";
+/// let v: Vec<_> = find_ips(line).map(|(r, ip)| (r, ip.to_string())).collect();
+/// assert_eq!(v, [(0..7, "1.2.3.4".to_string()), (35..46, "2001:db8::1".to_string()), (60..68, "10.0.0.1".to_string())]);
+/// ```
+pub fn find_ips(line: &[u8]) -> FindIps<'_> {
+    FindIps { line, pos: 0, ipv4_until: None }
+}
+
+/// The iterator returned by [find_ips]
+pub struct FindIps<'a> {
+    line: &'a [u8],
+    pos: usize,
+    // scanning the IPv4 literals of a run which is not an IPv6 literal
+    ipv4_until: Option<usize>,
+}
+
+fn is_ip_byte(b: u8) -> bool {
+    b.is_ascii_hexdigit() || b == b':' || b == b'.'
+}
+
+impl Iterator for FindIps<'_> {
+    type Item = (Range<usize>, std::net::IpAddr);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.line;
+        loop {
+            if let Some(end) = self.ipv4_until {
+                match find_ipv4(line, self.pos, end) {
+                    Some((r, ip)) => {
+                        self.pos = r.end;
+                        return Some((r, ip.into()));
+                    }
+                    None => {
+                        self.pos = end;
+                        self.ipv4_until = None;
+                    }
+                }
+            }
+            // the next run of hex digits, colons and dots
+            let start = self.pos + line.get(self.pos..)?.iter().position(|b| is_ip_byte(*b))?;
+            let end = line[start..].iter().position(|b| !is_ip_byte(*b)).map_or(line.len(), |n| start + n);
+            self.pos = start;
+            self.ipv4_until = Some(end);
+            let run = &line[start..end];
+            let glued = start > 0 && line[start - 1].is_ascii_alphanumeric()
+                || line.get(end).is_some_and(|b| b.is_ascii_alphanumeric());
+            if glued || !run.contains(&b':') {
+                continue;
+            }
+            // the trailing dot or colon, eg. "from ::1." or "2001:db8::1: refused"
+            let mut n = run.len();
+            while n > 0 && run[n - 1] == b'.' {
+                n -= 1;
+            }
+            if n > 1 && run[n - 1] == b':' && run[n - 2] != b':' {
+                n -= 1;
+            }
+            // a bare "::" is more likely a separator than the unspecified address
+            if !run[..n].iter().any(|b| b.is_ascii_hexdigit()) {
+                continue;
+            }
+            let ipv6 = std::str::from_utf8(&run[..n]).ok().and_then(|s| s.parse::<std::net::Ipv6Addr>().ok());
+            if let Some(ip) = ipv6 {
+                self.pos = start + n;
+                self.ipv4_until = None;
+                return Some((start..start + n, ip.into()));
+            }
+        }
+    }
+}
+
+// the first dotted quad in `line[from..to]`
+fn find_ipv4(line: &[u8], from: usize, to: usize) -> Option<(Range<usize>, std::net::Ipv4Addr)> {
+    let digit_at = |i: usize| line.get(i).is_some_and(|b| b.is_ascii_digit());
+    for i in from..to {
+        if !digit_at(i) || i > 0 && (line[i - 1].is_ascii_alphanumeric() || line[i - 1] == b'.') {
+            continue;
+        }
+        // 4 octets of 1 to 3 digits
+        let mut j = i;
+        let mut octets = 0;
+        while octets < 4 {
+            let n = (j..to).take_while(|k| digit_at(*k)).count();
+            if n == 0 || n > 3 {
+                break;
+            }
+            j += n;
+            octets += 1;
+            if octets < 4 {
+                if j >= to || line[j] != b'.' {
+                    break;
+                }
+                j += 1;
+            }
+        }
+        if octets < 4 {
+            continue;
+        }
+        // not followed by letters, digits or more octets like "1.2.3.4.5"
+        if line.get(j).is_some_and(|b| b.is_ascii_alphanumeric()) || line.get(j) == Some(&b'.') && digit_at(j + 1) {
+            continue;
+        }
+        if let Ok(ip) = std::str::from_utf8(&line[i..j]).unwrap_or_default().parse() {
+            return Some((i..j, ip));
+        }
+    }
+    None
+}
+
+/// The quoted and escaped JSON string, for the JSON written by hand, eg. of the codes from the input files.
+///
+/// ```
+/// use ip2c::util::json_str;
+///
+/// assert_eq!(json_str("CN"), r#""CN""#);
+/// assert_eq!(json_str("a\"b\\\n"), r#""a\"b\\\u000a""#);
+/// ```
+pub fn json_str(v: &str) -> String {
+    let mut s = String::with_capacity(v.len() + 2);
+    s.push('"');
+    for c in v.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(s, "\\u{:04x}", c as u32);
+            }
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cidr, Interval, IPv4, IPv6};
+    use crate::util::{parse_ipv4_ranges, ParseIpv4ScopeError, ParseIpv6ScopeError};
+
+    #[test]
+    fn lenient_prefix() {
+        // the host bits are masked whatever the length is, like `Cidr::parse_lenient`
+        for (s, cidr) in [("100.0.0.1/20", "100.0.0.0/20"), ("1.2.3.4/0", "0.0.0.0/0"), ("1.2.3.4/32", "1.2.3.4/32")] {
+            assert_eq!(s.parse::<Interval<IPv4>>().unwrap(), Cidr::<IPv4>::parse_lenient(s).unwrap().into());
+            assert_eq!(s.parse::<Interval<IPv4>>().unwrap(), cidr.parse::<Cidr<IPv4>>().unwrap().into());
+        }
+        for (s, cidr) in [("2001:db8::1/32", "2001:db8::/32"), ("2001:db8::1/0", "::/0"), ("[::1]/128", "::1/128")] {
+            assert_eq!(s.parse::<Interval<IPv6>>().unwrap(), Cidr::<IPv6>::parse_lenient(s).unwrap().into());
+            assert_eq!(s.parse::<Interval<IPv6>>().unwrap(), cidr.parse::<Cidr<IPv6>>().unwrap().into());
+        }
+        assert!(matches!("1.2.3.4/33".parse::<Interval<IPv4>>(), Err(ParseIpv4ScopeError::BadPrefixLength(_))));
+        assert!(matches!("::1/129".parse::<Interval<IPv6>>(), Err(ParseIpv6ScopeError::BadPrefixLength(_))));
+    }
+
+    #[test]
+    fn too_long_multi_byte() {
+        // the limit is in the middle of `é`
+        let s = format!("{}é.1.1.1", "1".repeat(30));
+        let e = s.parse::<Interval<IPv4>>().unwrap_err();
+        assert!(matches!(&e, ParseIpv4ScopeError::TooLong(i) if i.part() == "é.1.1.1"));
+        assert!(matches!(parse_ipv4_ranges(&s), Err(ParseIpv4ScopeError::TooLong(_))));
+
+        let s = format!("{}ü::1", ":".repeat(89));
+        let e = s.parse::<Interval<IPv6>>().unwrap_err();
+        assert!(matches!(&e, ParseIpv6ScopeError::TooLong(i) if i.part() == "ü::1" && i.span.start == 89));
+        assert!("地".repeat(40).parse::<Interval<IPv6>>().is_err());
     }
 }